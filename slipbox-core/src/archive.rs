@@ -0,0 +1,205 @@
+//! Reading a vault straight out of a tar archive, enabled by the `archive` feature.
+//!
+//! Only uncompressed tar archives are supported, as written by `tar -cf`. Both the POSIX ustar
+//! format and the GNU and pax extensions for long paths are understood. Zip archives and
+//! compressed tar archives are not supported.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use tracing::info;
+
+use crate::{Config, Error, Result, State, Vault};
+
+const BLOCK: usize = 512;
+
+/// Parse a NUL or space terminated octal number of a tar header.
+fn octal(field: &[u8]) -> Option<usize> {
+    let text = std::str::from_utf8(field).ok()?;
+    let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    match text.is_empty() {
+        true => Some(0),
+        false => usize::from_str_radix(text, 8).ok(),
+    }
+}
+
+/// A NUL terminated string field of a tar header.
+fn string(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// The `path` record of a pax extended header, if it has one. Records are `<len> key=value\n`.
+fn pax_path(data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data);
+    text.lines()
+        .filter_map(|record| record.split_once(' ').map(|(_, record)| record))
+        .find_map(|record| record.strip_prefix("path="))
+        .map(String::from)
+}
+
+/// All regular files in a tar archive as their path inside the archive and their contents.
+fn entries(archive: &Path, data: &[u8]) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let corrupt = |msg: &str| Error::ArchiveError(archive.to_path_buf(), String::from(msg));
+    if data.starts_with(b"PK\x03\x04") {
+        return Err(corrupt("zip archives aren't supported, only tar archives"));
+    }
+    let mut entries = vec![];
+    let mut long_name: Option<String> = None;
+    let mut offset = 0;
+
+    while offset + BLOCK <= data.len() {
+        let header = &data[offset..offset + BLOCK];
+        // The archive ends with two empty blocks.
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+
+        let size = octal(&header[124..136]).ok_or_else(|| corrupt("invalid entry size"))?;
+        let start = offset + BLOCK;
+        let contents = data
+            .get(start..start + size)
+            .ok_or_else(|| corrupt("truncated entry"))?;
+        offset = start + (size + BLOCK - 1) / BLOCK * BLOCK;
+
+        match header[156] {
+            b'L' => long_name = Some(string(contents)),
+            b'x' => long_name = pax_path(contents).or(long_name),
+            b'0' | 0 => {
+                let name = match long_name.take() {
+                    Some(name) => name,
+                    None if &header[257..262] == b"ustar" && header[345] != 0 => {
+                        format!("{}/{}", string(&header[345..500]), string(&header[..100]))
+                    }
+                    None => string(&header[..100]),
+                };
+                entries.push((PathBuf::from(name), contents.to_vec()));
+            }
+            // Directories, links and everything else don't contain notes.
+            _ => long_name = None,
+        }
+    }
+
+    Ok(entries)
+}
+
+impl Vault {
+    /// Load a vault from an uncompressed tar archive of it without extracting it first. The notes
+    /// are parsed like the files of a vault directory, but [`crate::Note::path`] is the path inside
+    /// the archive and the contents of every note are kept in memory. Entries that aren't notes or
+    /// are hidden are skipped. The state isn't backed by a directory, so [`State::refresh`] fails.
+    /// Zip archives are not supported, they fail with [`Error::ArchiveError`] just like a corrupt
+    /// tar archive does.
+    pub fn from_archive(path: impl AsRef<Path>, config: Config) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let data = fs::read(&path).map_err(|e| Error::io(&path, e))?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        info!("Vault-name: {:?}", name);
+
+        let mut sources = vec![];
+        let mut errors = vec![];
+        for (entry, contents) in entries(&path, &data)? {
+            // Skip `./` and similar, and anything hidden along the way.
+            let entry = entry
+                .components()
+                .filter(|c| matches!(c, Component::Normal(_)))
+                .collect::<PathBuf>();
+            let skipped = entry
+                .ancestors()
+                .filter(|p| !p.as_os_str().is_empty())
+                .any(|p| config.is_ignored(p));
+            if skipped || !config.is_note(&entry) {
+                continue;
+            }
+            match String::from_utf8(contents) {
+                Ok(contents) => sources.push((entry, contents)),
+                Err(e) => errors.push((entry.clone(), Error::FileIo(entry, e.to_string()))),
+            }
+        }
+
+        let mut state = State::_from_contents(PathBuf::new(), config, sources);
+        state.errors.extend(errors);
+        Ok(Self {
+            vault_path: path,
+            name,
+            created_on: None,
+            state,
+            templates: HashMap::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_vault;
+
+    fn tar_entry(name: &str, kind: u8, contents: &[u8]) -> Vec<u8> {
+        let mut header = vec![0; BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[124..135].copy_from_slice(format!("{:011o}", contents.len()).as_bytes());
+        header[156] = kind;
+        header[257..263].copy_from_slice(b"ustar\0");
+        let mut entry = header;
+        entry.extend_from_slice(contents);
+        entry.resize((entry.len() + BLOCK - 1) / BLOCK * BLOCK, 0);
+        entry
+    }
+
+    #[test]
+    fn test_from_archive() -> Result<()> {
+        let path = scratch_vault("archive");
+        let long_name = format!("notes/{}/Deep.md", "nested".repeat(20));
+        let mut data = vec![];
+        data.extend(tar_entry("notes/", b'5', b""));
+        data.extend(tar_entry(
+            "notes/A.md",
+            b'0',
+            b"---\ntags: a\n---\nSee [[B]] and [[Deep]].",
+        ));
+        data.extend(tar_entry("./notes/B.md", b'0', b"Back to [[A]]."));
+        data.extend(tar_entry("notes/.trash/C.md", b'0', b""));
+        data.extend(tar_entry("notes/image.png", b'0', &[0xff, 0xd8]));
+        data.extend(tar_entry("././@LongLink", b'L', long_name.as_bytes()));
+        data.extend(tar_entry("notes/nestednested", b'0', b"Deep down."));
+        data.extend(vec![0; 2 * BLOCK]);
+        fs::write(path.join("vault.tar"), &data)?;
+
+        let vault = Vault::from_archive(path.join("vault.tar"), Config::default())?;
+        assert!(vault.name == "vault");
+        let paths = vault
+            .state
+            .iter()
+            .map(|note| note.path.clone())
+            .collect::<Vec<_>>();
+        assert!(
+            paths
+                == [
+                    PathBuf::from("notes/A.md"),
+                    PathBuf::from("notes/B.md"),
+                    PathBuf::from(&long_name)
+                ]
+        );
+        assert!(vault.state.notes[0].tags == ["a"] && vault.state.notes[0].links.len() == 2);
+        assert!(vault.state.notes[1].content()? == "Back to [[A]].");
+        assert!(vault.state.notes[0].id == "notes-a");
+
+        fs::write(path.join("vault.tar"), &data[..2 * BLOCK + 10])?;
+        match Vault::from_archive(path.join("vault.tar"), Config::default()) {
+            Err(Error::ArchiveError(_, msg)) => assert!(msg == "truncated entry"),
+            _ => panic!("A truncated archive should fail"),
+        }
+        fs::write(path.join("vault.zip"), b"PK\x03\x04rest of a zip file")?;
+        assert!(matches!(
+            Vault::from_archive(path.join("vault.zip"), Config::default()),
+            Err(Error::ArchiveError(_, _))
+        ));
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+}
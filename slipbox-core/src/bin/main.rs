@@ -1,19 +1,352 @@
 extern crate slipbox_core;
 
-
+use std::ffi::OsString;
 use std::path::PathBuf;
+use std::process::ExitCode;
+
+use slipbox_core::{init_tracing, Error, IssueCategory, Result, ValidationIssue, Vault};
+use tracing::Level;
+
+const USAGE: &str = "\
+usage: slipbox <command> [--vault <path>] [--json] [--verbose]
+
+commands:
+    list              list all notes with their tags
+    tags              list all tags with the number of notes carrying them
+    search <query>    list the notes containing the words of the query, best match first
+    orphans           list the notes that neither link to nor are linked from another note
+    validate          report every problem with the notes of the vault
+
+options:
+    --vault <path>    the vault to use, defaults to $SLIPBOX_VAULT or else the current directory
+    --json            print the output as JSON, needs the `serde` feature
+    --verbose         log what's happening while loading the vault";
+
+/// The environment variable holding the vault to use when there's no `--vault` option.
+const VAULT_VAR: &str = "SLIPBOX_VAULT";
+
+/// The subcommands of the binary, see [`USAGE`].
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    List,
+    Tags,
+    /// The words of the query, joined by spaces.
+    Search(String),
+    Orphans,
+    Validate,
+}
+
+/// The parsed command line.
+struct Args {
+    command: Command,
+    vault: Option<PathBuf>,
+    json: bool,
+    verbose: bool,
+}
+
+/// The output of a command, printed either for humans or as JSON.
+enum Report {
+    /// Notes with their display tags, by path relative to the vault.
+    Notes(Vec<(PathBuf, Vec<String>)>),
+    /// Tags with the number of notes carrying them, most common first.
+    Tags(Vec<(String, usize)>),
+    /// Notes by path relative to the vault.
+    Paths(Vec<PathBuf>),
+    /// Problems with the notes, by path relative to the vault.
+    Issues(Vec<ValidationIssue>),
+}
+
+/// Parse the arguments following the name of the binary. `None` means the usage was asked for.
+fn parse_args(mut args: impl Iterator<Item = String>) -> std::result::Result<Option<Args>, String> {
+    let mut command = None;
+    let mut operands = vec![];
+    let mut vault = None;
+    let mut json = false;
+    let mut verbose = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--vault" => match args.next() {
+                Some(path) => vault = Some(PathBuf::from(path)),
+                None => return Err(String::from("--vault needs a path")),
+            },
+            "--json" if !cfg!(feature = "serde") => {
+                return Err(String::from(
+                    "--json is only available when built with the `serde` feature",
+                ))
+            }
+            "--json" => json = true,
+            "--verbose" | "-v" => verbose = true,
+            "--help" | "-h" => return Ok(None),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ if command.is_none() => command = Some(arg),
+            _ => operands.push(arg),
+        }
+    }
+
+    let command = match (command.as_deref(), operands.is_empty()) {
+        (None, _) => return Err(String::from("missing command")),
+        (Some("search"), _) if !cfg!(feature = "search") => {
+            return Err(String::from(
+                "search is only available when built with the `search` feature",
+            ))
+        }
+        (Some("search"), true) => return Err(String::from("search needs a query")),
+        (Some("search"), false) => Command::Search(operands.join(" ")),
+        (Some("list"), true) => Command::List,
+        (Some("tags"), true) => Command::Tags,
+        (Some("orphans"), true) => Command::Orphans,
+        (Some("validate"), true) => Command::Validate,
+        (Some(name @ ("list" | "tags" | "orphans" | "validate")), false) => {
+            return Err(format!("{} doesn't take any arguments", name))
+        }
+        (Some(name), _) => return Err(format!("unknown command {}", name)),
+    };
+
+    Ok(Some(Args {
+        command,
+        vault,
+        json,
+        verbose,
+    }))
+}
 
-use slipbox_core::{init_tracing, Result, Vault};
-use tracing::info;
+/// Run the command and return whether it succeeded, printing its output along the way.
+fn run(args: Args) -> Result<bool> {
+    let path = vault_path(args.vault, std::env::var_os(VAULT_VAR))?;
 
+    // Validation reports broken notes itself instead of failing to open the vault.
+    if args.command == Command::Validate {
+        let mut issues = Vault::validate(&path);
+        // Print the notes relative to the vault like the other commands do.
+        for issue in &mut issues {
+            if issue.category != IssueCategory::InvalidVault {
+                if let Ok(relative) = issue.path.strip_prefix(&path) {
+                    issue.path = relative.to_path_buf();
+                }
+            }
+        }
+        let ok = issues.is_empty();
+        print(&Report::Issues(issues), args.json)?;
+        return Ok(ok);
+    }
 
-fn main() -> Result<()> {
-    init_tracing();
+    let vault = Vault::open(&path)?;
+    let state = &vault.state;
+    let report = match &args.command {
+        Command::List => Report::Notes(
+            state
+                .iter()
+                .map(|note| (note.relative_path(&vault), note.display_tags.clone()))
+                .collect(),
+        ),
+        Command::Tags => {
+            // Show the tags the way they are written, like `list` does.
+            let spellings = state.tag_spellings();
+            let mut counts = state
+                .tag_counts()
+                .into_iter()
+                .map(|(tag, count)| (String::from(spellings[tag.as_str()]), count))
+                .collect::<Vec<_>>();
+            counts
+                .sort_by_cached_key(|(tag, count)| (std::cmp::Reverse(*count), tag.to_lowercase()));
+            Report::Tags(counts)
+        }
+        Command::Search(query) => Report::Paths(search(&vault, query)),
+        Command::Orphans => Report::Paths(
+            state
+                .orphans()
+                .iter()
+                .map(|note| note.relative_path(&vault))
+                .collect(),
+        ),
+        Command::Validate => unreachable!("validation is handled above"),
+    };
+    print(&report, args.json)?;
 
-    let vault = Vault::new(PathBuf::from("./slipbox-core/tests/vault"))?;
+    if state.has_errors() {
+        eprintln!(
+            "{} notes failed to load, run `slipbox validate` for details",
+            state.errors.len()
+        );
+    }
+    Ok(true)
+}
 
-    info!("{:#?}", vault);
+/// The vault given by the `--vault` option, the value `env` of the [`VAULT_VAR`] environment
+/// variable or the current directory, whichever is set first. An empty variable counts as unset.
+/// Fails if it isn't a directory, naming where it came from.
+fn vault_path(option: Option<PathBuf>, env: Option<OsString>) -> Result<PathBuf> {
+    let from_env = env.filter(|path| !path.is_empty());
+    let (path, source) = match (option, from_env) {
+        (Some(path), _) => (path, "--vault"),
+        (None, Some(path)) => (PathBuf::from(path), VAULT_VAR),
+        (None, None) => (std::env::current_dir()?, "the current directory"),
+    };
+    let problem = match path.metadata() {
+        Ok(metadata) if metadata.is_dir() => return Ok(path),
+        Ok(_) => "not a directory",
+        Err(_) => "not found",
+    };
+    Err(Error::InvalidPath(
+        path,
+        format!("{}, taken from {}", problem, source),
+    ))
+}
 
+/// The notes matching `query`, best match first.
+#[cfg(feature = "search")]
+fn search(vault: &Vault, query: &str) -> Vec<PathBuf> {
+    let notes = vault.state.search(query);
+    notes.iter().map(|note| note.relative_path(vault)).collect()
+}
 
+#[cfg(not(feature = "search"))]
+fn search(_vault: &Vault, _query: &str) -> Vec<PathBuf> {
+    unreachable!("search is rejected without the search feature")
+}
+
+fn print(report: &Report, json: bool) -> Result<()> {
+    if json {
+        println!("{}", to_json(report)?);
+        return Ok(());
+    }
+    match report {
+        Report::Notes(notes) => {
+            for (path, tags) in notes {
+                match tags.is_empty() {
+                    true => println!("{}", path.display()),
+                    false => println!("{}  [{}]", path.display(), tags.join(", ")),
+                }
+            }
+        }
+        Report::Tags(counts) => {
+            for (tag, count) in counts {
+                println!("{:>5}  {}", count, tag);
+            }
+        }
+        Report::Paths(paths) => {
+            for path in paths {
+                println!("{}", path.display());
+            }
+        }
+        Report::Issues(issues) => {
+            for issue in issues {
+                println!("{}", issue);
+            }
+            match issues.len() {
+                0 => println!("no problems found"),
+                1 => println!("1 problem found"),
+                n => println!("{} problems found", n),
+            }
+        }
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// The report as a single line of JSON: an array of `{"path", "tags"}` objects for notes, of
+/// `{"tag", "count"}` objects for tags, of strings for paths and of issues as they serialize.
+#[cfg(feature = "serde")]
+fn to_json(report: &Report) -> Result<String> {
+    use serde_json::json;
+
+    let value = match report {
+        Report::Notes(notes) => notes
+            .iter()
+            .map(|(path, tags)| json!({ "path": path, "tags": tags }))
+            .collect(),
+        Report::Tags(counts) => counts
+            .iter()
+            .map(|(tag, count)| json!({ "tag": tag, "count": count }))
+            .collect(),
+        Report::Paths(paths) => json!(paths),
+        Report::Issues(issues) => json!(issues),
+    };
+    Ok(serde_json::to_string(&value)?)
+}
+
+#[cfg(not(feature = "serde"))]
+fn to_json(_report: &Report) -> Result<String> {
+    unreachable!("--json is rejected without the serde feature")
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(msg) => {
+            eprintln!("{}\n\n{}", msg, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    if args.verbose {
+        init_tracing(Level::INFO);
+    }
+
+    match run(args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> std::result::Result<Option<Args>, String> {
+        parse_args(args.iter().map(|arg| String::from(*arg)))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = parse(&["list", "--vault", "notes", "-v"]).unwrap().unwrap();
+        assert!(args.command == Command::List);
+        assert!(args.vault == Some(PathBuf::from("notes")) && args.verbose && !args.json);
+        assert!(parse(&["validate"]).unwrap().unwrap().command == Command::Validate);
+        assert!(parse(&["list", "--help"]).unwrap().is_none());
+        #[cfg(feature = "search")]
+        {
+            let args = parse(&["search", "rust", "--vault", "notes", "async"]).unwrap();
+            assert!(args.unwrap().command == Command::Search(String::from("rust async")));
+        }
+
+        let error = |args: &[&str]| parse(args).err();
+        assert!(error(&["list", "--vault"]).as_deref() == Some("--vault needs a path"));
+        assert!(error(&["list", "--colour"]).as_deref() == Some("unknown option --colour"));
+        assert!(error(&["tags", "rust"]).as_deref() == Some("tags doesn't take any arguments"));
+        assert!(
+            error(&["bogus", "--vault", "/missing"]).as_deref() == Some("unknown command bogus")
+        );
+        assert!(error(&[]).as_deref() == Some("missing command"));
+        #[cfg(feature = "search")]
+        assert!(error(&["search"]).as_deref() == Some("search needs a query"));
+        #[cfg(not(feature = "search"))]
+        assert!(error(&["search", "rust"]).is_some());
+    }
+
+    #[test]
+    fn test_vault_path() -> Result<()> {
+        let vault = || Some(PathBuf::from("tests/vault"));
+        let env = |value: &str| Some(OsString::from(value));
+        assert!(vault_path(vault(), env("/missing")).ok() == vault());
+        assert!(vault_path(None, env("tests/vault")).ok() == vault());
+        assert!(vault_path(None, env(""))? == std::env::current_dir()?);
+        assert!(vault_path(None, None)? == std::env::current_dir()?);
+
+        let error = vault_path(Some(PathBuf::from("/missing")), None).unwrap_err();
+        assert!(error.to_string() == "invalid vault path /missing: not found, taken from --vault");
+        let error = vault_path(None, env("Cargo.toml")).unwrap_err();
+        assert!(
+            error.to_string()
+                == "invalid vault path Cargo.toml: not a directory, taken from SLIPBOX_VAULT"
+        );
+        Ok(())
+    }
+}
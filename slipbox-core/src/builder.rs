@@ -0,0 +1,178 @@
+//! Setting the options of a vault one at a time before opening it.
+
+use std::path::{Path, PathBuf};
+
+use crate::{Config, Result, Vault};
+
+/// Builds a [`Vault`] with chained setters for the options of its [`Config`], starting from the
+/// default options.
+///
+/// ```no_run
+/// # use slipbox_core::VaultBuilder;
+/// let vault = VaultBuilder::new("notes")
+///     .tag_key("keywords")
+///     .extensions(["md", "markdown"])
+///     .require_tags(true)
+///     .open()?;
+/// # Ok::<(), slipbox_core::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct VaultBuilder {
+    path: PathBuf,
+    config: Config,
+}
+
+impl VaultBuilder {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            config: Config::default(),
+        }
+    }
+
+    /// Replace all options at once, e.g. with the ones read by [`Config::from_file`].
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// See [`Config::tag_key`]. The trailing `:` may be left out.
+    pub fn tag_key(mut self, tag_key: &str) -> Self {
+        self.config.tag_key = match tag_key.ends_with(':') {
+            true => String::from(tag_key),
+            false => format!("{}:", tag_key),
+        };
+        self
+    }
+
+    /// See [`Config::require_tags`].
+    pub fn require_tags(mut self, require_tags: bool) -> Self {
+        self.config.require_tags = require_tags;
+        self
+    }
+
+    /// See [`Config::extensions`].
+    pub fn extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.extensions = extensions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// See [`Config::cache_content`].
+    pub fn cache_content(mut self, cache_content: bool) -> Self {
+        self.config.cache_content = cache_content;
+        self
+    }
+
+    /// See [`Config::unique_names`].
+    pub fn unique_names(mut self, unique_names: bool) -> Self {
+        self.config.unique_names = unique_names;
+        self
+    }
+
+    /// See [`Config::skip_hidden`].
+    pub fn skip_hidden(mut self, skip_hidden: bool) -> Self {
+        self.config.skip_hidden = skip_hidden;
+        self
+    }
+
+    /// See [`Config::backup_suffixes`].
+    pub fn backup_suffixes<I, S>(mut self, suffixes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.backup_suffixes = suffixes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// See [`Config::allowed_tags`].
+    pub fn allowed_tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.allowed_tags = Some(tags.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// See [`Config::folder_tags`].
+    pub fn folder_tags(mut self, folder_tags: bool) -> Self {
+        self.config.folder_tags = folder_tags;
+        self
+    }
+
+    /// See [`Config::inline_tags`].
+    pub fn inline_tags(mut self, inline_tags: bool) -> Self {
+        self.config.inline_tags = inline_tags;
+        self
+    }
+
+    /// See [`Config::max_depth`].
+    pub fn max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.config.max_depth = max_depth;
+        self
+    }
+
+    /// See [`Config::templates_dir`].
+    pub fn templates_dir(mut self, templates_dir: Option<&str>) -> Self {
+        self.config.templates_dir = templates_dir.map(String::from);
+        self
+    }
+
+    /// Load the vault with the chosen options.
+    pub fn open(self) -> Result<Vault> {
+        Vault::with_config(self.path, self.config)
+    }
+}
+
+impl Vault {
+    /// Start building a vault at `path`, see [`VaultBuilder`].
+    pub fn builder(path: impl AsRef<Path>) -> VaultBuilder {
+        VaultBuilder::new(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_vault;
+    use std::fs;
+
+    #[test]
+    fn test_builder() -> Result<()> {
+        let path = scratch_vault("builder");
+        fs::create_dir_all(path.join("projects"))?;
+        fs::write(path.join("projects/A.md"), "---\nkeywords: rust\n---\n")?;
+        fs::write(path.join("B.markdown"), "---\nkeywords: notes\n---\n")?;
+        fs::write(path.join("C.md"), "no tags")?;
+
+        let vault = Vault::builder(path.clone())
+            .tag_key("keywords")
+            .extensions(["md", "markdown"])
+            .folder_tags(true)
+            .cache_content(true)
+            .open()?;
+        assert!(vault.state.config().tag_key == "keywords:");
+        assert!(vault.state.len() == 3);
+        assert!(vault.state.note_by_name("A").unwrap().tags == ["rust", "projects"]);
+        assert!(vault.state.note_by_name("B").unwrap().cached_content.is_some());
+
+        let vault = VaultBuilder::new(path.clone())
+            .tag_key("keywords:")
+            .require_tags(true)
+            .open()?;
+        assert!(vault.state.len() == 1 && vault.state.errors.len() == 1);
+
+        let vault = Vault::builder(path.clone()).max_depth(Some(0)).open()?;
+        assert!(vault.state.len() == 1 && vault.state.notes[0].name == "C");
+        assert!(!vault.state.is_tracked(&path.join("projects/D.md")));
+        assert!(vault.state.is_tracked(&path.join("D.md")));
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+use std::fs;
+use std::path::Path;
+
+use crate::{Error, Result};
+
+/// The key that introduces the tags of a note in its metadata block, if not configured otherwise.
+pub const DEFAULT_TAG_KEY: &str = "tags:";
+
+/// Name of the optional config file at the root of a vault, read by [`crate::Vault::open`].
+pub const CONFIG_FILE: &str = ".slipbox.toml";
+
+/// Name of the optional file at the root of a vault listing the allowed tags, one per line, read
+/// by [`crate::Vault::open`] unless the config file already sets [`Config::allowed_tags`].
+pub const TAGS_FILE: &str = "tags.txt";
+
+/// Name of the optional directory at the root of a vault holding the templates of
+/// [`crate::Vault::create_from_template`], unless [`Config::templates_dir`] names another one.
+pub const TEMPLATES_DIR: &str = "templates";
+
+/// Options controlling how the notes of a vault are parsed.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+    /// The key of the metadata line listing the tags of a note, e.g. `tags:` or `keywords:`.
+    pub tag_key: String,
+    /// Treat notes without any tags as errors instead of loading them with no tags.
+    pub require_tags: bool,
+    /// File extensions of notes, without the leading dot. Matched case-insensitively. Files ending
+    /// in `.org` are parsed as Org-mode documents, all others as markdown.
+    pub extensions: Vec<String>,
+    /// Keep the contents of every note in memory after loading, so [`crate::Note::content`] doesn't
+    /// have to read the file again. Off by default to save memory on large vaults.
+    pub cache_content: bool,
+    /// Fail to load the vault if several notes share a name, instead of only recording the
+    /// [`crate::Error::DuplicateNoteName`] in [`crate::State::conflicts`].
+    pub unique_names: bool,
+    /// Skip files and directories whose name starts with a `.`, like `.trash/` or `.obsidian/`.
+    /// On by default.
+    pub skip_hidden: bool,
+    /// Files ending in one of these suffixes are editor swap or backup files and never notes, e.g.
+    /// `note.md~` or `.note.md.swp`.
+    pub backup_suffixes: Vec<String>,
+    /// The controlled vocabulary of the vault. If set, [`crate::State::unknown_tags`] reports
+    /// every tag that isn't in the list. Matched case-insensitively.
+    pub allowed_tags: Option<Vec<String>>,
+    /// Tag every note with the folders it's in, so a note in `projects/rust/` also gets the tags
+    /// `projects` and `rust`. The folder tags come after the declared tags of the note.
+    pub folder_tags: bool,
+    /// Also tag notes with the `#tags` written anywhere in their body, like Obsidian does. The
+    /// inline tags come after the tags of the metadata block. Only applies to markdown notes.
+    pub inline_tags: bool,
+    /// How many levels of directories below the root of the vault are scanned for notes. `0` only
+    /// scans the root itself, `None`, the default, scans all of them.
+    pub max_depth: Option<usize>,
+    /// The directory at the root of the vault holding the templates of
+    /// [`crate::Vault::create_from_template`]. Setting it also leaves the directory out of the
+    /// scan, so templates aren't loaded as notes. `None`, the default, reads templates from
+    /// [`TEMPLATES_DIR`] but scans it like any other directory, so notes kept there still load.
+    pub templates_dir: Option<String>,
+}
+
+impl Config {
+    /// Whether the file or directory at `path` is skipped when scanning a vault because it's hidden
+    /// or a backup file, see [`Config::skip_hidden`] and [`Config::backup_suffixes`].
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy(),
+            None => return false,
+        };
+        (self.skip_hidden && name.starts_with('.'))
+            || self
+                .backup_suffixes
+                .iter()
+                .any(|suffix| name.ends_with(suffix.as_str()))
+    }
+
+    /// Read a config file like [`CONFIG_FILE`]. Only a small subset of TOML is supported:
+    /// `key = value` lines with strings, booleans and single-line arrays of strings, and `#`
+    /// comments. Options that aren't set keep their default. Example:
+    ///
+    /// ```toml
+    /// tag_key = "keywords"
+    /// extensions = ["md", "markdown"]
+    /// require_tags = true
+    /// ```
+    ///
+    /// A `tag_key` without a trailing `:` gets one appended.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        Self::_parse(&text).map_err(|msg| Error::ConfigError(path.to_path_buf(), msg))
+    }
+
+    fn _parse(text: &str) -> std::result::Result<Self, String> {
+        let mut config = Self::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = Self::_strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected `key = value`", i + 1))?;
+            let (key, value) = (key.trim(), value.trim());
+            let invalid = |expected: &str| format!("line {}: {} must be {}", i + 1, key, expected);
+
+            match key {
+                "tag_key" => {
+                    let tag_key = Self::_parse_string(value).ok_or_else(|| invalid("a string"))?;
+                    config.tag_key = match tag_key.ends_with(':') {
+                        true => tag_key,
+                        false => format!("{}:", tag_key),
+                    };
+                }
+                "extensions" => {
+                    config.extensions =
+                        Self::_parse_array(value).ok_or_else(|| invalid("an array of strings"))?;
+                }
+                "require_tags" => {
+                    config.require_tags =
+                        Self::_parse_bool(value).ok_or_else(|| invalid("true or false"))?;
+                }
+                "cache_content" => {
+                    config.cache_content =
+                        Self::_parse_bool(value).ok_or_else(|| invalid("true or false"))?;
+                }
+                "unique_names" => {
+                    config.unique_names =
+                        Self::_parse_bool(value).ok_or_else(|| invalid("true or false"))?;
+                }
+                "skip_hidden" => {
+                    config.skip_hidden =
+                        Self::_parse_bool(value).ok_or_else(|| invalid("true or false"))?;
+                }
+                "folder_tags" => {
+                    config.folder_tags =
+                        Self::_parse_bool(value).ok_or_else(|| invalid("true or false"))?;
+                }
+                "inline_tags" => {
+                    config.inline_tags =
+                        Self::_parse_bool(value).ok_or_else(|| invalid("true or false"))?;
+                }
+                "max_depth" => {
+                    let max_depth = value
+                        .parse()
+                        .map_err(|_| invalid("a non-negative integer"))?;
+                    config.max_depth = Some(max_depth);
+                }
+                "templates_dir" => {
+                    config.templates_dir =
+                        Some(Self::_parse_string(value).ok_or_else(|| invalid("a string"))?);
+                }
+                "backup_suffixes" => {
+                    config.backup_suffixes =
+                        Self::_parse_array(value).ok_or_else(|| invalid("an array of strings"))?;
+                }
+                "allowed_tags" => {
+                    config.allowed_tags = Some(
+                        Self::_parse_array(value).ok_or_else(|| invalid("an array of strings"))?,
+                    );
+                }
+                _ => return Err(format!("line {}: unknown option {:?}", i + 1, key)),
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Read a [`TAGS_FILE`] listing one allowed tag per line. Blank lines and lines starting with
+    /// `#` are skipped.
+    pub fn read_tags_file(path: &Path) -> Result<Vec<String>> {
+        let text = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        Ok(text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect())
+    }
+
+    /// Remove a `#` comment from a line, unless the `#` is part of a string.
+    fn _strip_comment(line: &str) -> &str {
+        let mut quote: Option<char> = None;
+        for (i, c) in line.char_indices() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '#') => return &line[..i],
+                _ => {}
+            }
+        }
+        line
+    }
+
+    fn _parse_string(value: &str) -> Option<String> {
+        ['"', '\'']
+            .iter()
+            .find_map(|&q| value.strip_prefix(q).and_then(|v| v.strip_suffix(q)))
+            .filter(|v| !v.contains(['"', '\'']))
+            .map(String::from)
+    }
+
+    fn _parse_array(value: &str) -> Option<Vec<String>> {
+        let items = value.strip_prefix('[')?.strip_suffix(']')?.trim();
+        items
+            .split(',')
+            .map(str::trim)
+            // A trailing comma is allowed.
+            .filter(|item| !item.is_empty())
+            .map(Self::_parse_string)
+            .collect()
+    }
+
+    fn _parse_bool(value: &str) -> Option<bool> {
+        match value {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Whether the file at `path` has one of the configured note extensions.
+    pub fn is_note(&self, path: &Path) -> bool {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(extension) => self
+                .extensions
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(extension)),
+            None => false,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tag_key: String::from(DEFAULT_TAG_KEY),
+            require_tags: false,
+            extensions: vec![String::from("md"), String::from("org")],
+            cache_content: false,
+            unique_names: false,
+            skip_hidden: true,
+            backup_suffixes: ["~", ".swp", ".swo", ".bak", ".orig"]
+                .map(String::from)
+                .to_vec(),
+            allowed_tags: None,
+            folder_tags: false,
+            inline_tags: false,
+            max_depth: None,
+            templates_dir: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::_parse(
+            "# vault options\ntag_key = \"keywords\"\n\nextensions = ['md', \"markdown\",]\n\
+             require_tags = true # strict\n",
+        )
+        .unwrap();
+        assert!(config.tag_key == "keywords:");
+        assert!(config.extensions == ["md", "markdown"]);
+        assert!(config.require_tags && !config.cache_content);
+
+        assert!(Config::_parse("").unwrap().tag_key == DEFAULT_TAG_KEY);
+        assert!(Config::_parse("tag_key = \"#tags:\"").unwrap().tag_key == "#tags:");
+        assert!(Config::_parse("folder_tags = true").unwrap().folder_tags);
+        assert!(Config::_parse("inline_tags = true").unwrap().inline_tags);
+        assert!(Config::_parse("max_depth = 2").unwrap().max_depth == Some(2));
+        let config = Config::_parse("templates_dir = '_templates'").unwrap();
+        assert!(config.templates_dir.as_deref() == Some("_templates"));
+        assert!(Config::_parse("").unwrap().templates_dir.is_none());
+        let errors = [
+            "require_tags = yes",
+            "colour = \"red\"",
+            "extensions",
+            "max_depth = -1",
+        ]
+        .map(|text| Config::_parse(text).unwrap_err());
+        assert!(errors[0] == "line 1: require_tags must be true or false");
+        assert!(errors[1] == "line 1: unknown option \"colour\"");
+        assert!(errors[2] == "line 1: expected `key = value`");
+        assert!(errors[3] == "line 1: max_depth must be a non-negative integer");
+    }
+}
@@ -0,0 +1,162 @@
+//! Minimal calendar date handling, so that no date library is needed for the few places where notes
+//! carry dates.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Convert days since the unix epoch into a `(year, month, day)` civil date.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Convert a `(year, month, day)` civil date into days since the unix epoch.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Parse a fixed number of ASCII digits.
+fn digits(text: &str) -> Option<u32> {
+    match !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit()) {
+        true => text.parse().ok(),
+        false => None,
+    }
+}
+
+/// Parse an ISO 8601 date like `2024-03-01`, optionally followed by a time like `T14:30`,
+/// `T14:30:15.250` or ` 14:30:15` and a UTC offset like `Z` or `+02:00`. Dates without an offset
+/// are taken as UTC, dates without a time as midnight.
+pub(crate) fn parse_date(text: &str) -> Option<SystemTime> {
+    let text = text.trim();
+    let (date, time) = match text.find(['T', 't', ' ']) {
+        Some(i) => (&text[..i], Some(text[i + 1..].trim_start())),
+        None => (text, None),
+    };
+
+    let mut parts = date.splitn(3, '-');
+    let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return None;
+    }
+    let (year, month, day) = (i64::from(digits(year)?), digits(month)?, digits(day)?);
+    // Round trip through the day count to reject dates like February 30th.
+    let days = days_from_civil(year, month, day);
+    if !(1..=12).contains(&month) || civil_from_days(days) != (year, month, day) {
+        return None;
+    }
+
+    let mut secs = days * 86_400;
+    let mut nanos = 0;
+    if let Some(time) = time {
+        let (time, offset) = match time.find(['Z', 'z', '+', '-']) {
+            Some(i) => time.split_at(i),
+            None => (time, ""),
+        };
+        let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+        let fields = time.split(':').collect::<Vec<_>>();
+        if !(2..=3).contains(&fields.len()) || fields.iter().any(|field| field.len() != 2) {
+            return None;
+        }
+        let hours = digits(fields[0])?;
+        let minutes = digits(fields[1])?;
+        let seconds = fields.get(2).map_or(Some(0), |field| digits(field))?;
+        if hours > 23 || minutes > 59 || seconds > 60 {
+            return None;
+        }
+        secs += i64::from(hours * 3600 + minutes * 60 + seconds);
+
+        if !fraction.is_empty() {
+            digits(fraction)?;
+            let fraction = format!("{:0<9}", &fraction[..fraction.len().min(9)]);
+            nanos = fraction.parse::<u32>().ok()?;
+        }
+
+        match offset {
+            "" | "Z" | "z" => {}
+            offset => {
+                let sign = if offset.starts_with('-') { -1 } else { 1 };
+                let offset = offset[1..].replace(':', "");
+                if !offset.is_ascii() || offset.len() != 4 {
+                    return None;
+                }
+                let (hours, minutes) = (digits(&offset[..2])?, digits(&offset[2..])?);
+                secs -= sign * i64::from(hours * 3600 + minutes * 60);
+            }
+        }
+    }
+
+    let since_epoch = Duration::new(secs.unsigned_abs(), 0);
+    let time = match secs >= 0 {
+        true => UNIX_EPOCH.checked_add(since_epoch)?,
+        false => UNIX_EPOCH.checked_sub(since_epoch)?,
+    };
+    time.checked_add(Duration::from_nanos(u64::from(nanos)))
+}
+
+/// Format the date of a point in time as `YYYY-MM-DD` in UTC.
+pub(crate) fn format_date(time: SystemTime) -> String {
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_date() {
+        assert!(format_date(UNIX_EPOCH) == "1970-01-01");
+        assert!(format_date(UNIX_EPOCH + Duration::from_secs(951_782_400)) == "2000-02-29");
+        assert!(format_date(UNIX_EPOCH + Duration::from_secs(1_791_936_000)) == "2026-10-14");
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let at = |secs: u64| Some(UNIX_EPOCH + Duration::from_secs(secs));
+        assert!(parse_date("1970-01-01") == Some(UNIX_EPOCH));
+        assert!(parse_date("2000-02-29") == at(951_782_400));
+        assert!(parse_date("2026-10-14T00:00:00Z") == at(1_791_936_000));
+        assert!(parse_date("2026-10-14 02:30") == at(1_791_936_000 + 9000));
+        assert!(parse_date("2026-10-14T02:30:00+02:30") == at(1_791_936_000));
+        assert!(parse_date("2026-10-13T21:00:00-0300") == at(1_791_936_000));
+        assert!(
+            parse_date("1970-01-01T00:00:01.25Z") == Some(UNIX_EPOCH + Duration::from_millis(1250))
+        );
+        assert!(parse_date("1969-12-31") == UNIX_EPOCH.checked_sub(Duration::from_secs(86_400)));
+
+        for invalid in [
+            "",
+            "yesterday",
+            "2023-02-29",
+            "2024-13-01",
+            "2024-1-01",
+            "2024-01-01T25:00",
+            "2024-01-01T10",
+            "2024-01-01T10:00+2",
+            "2024-01-01T10:00+aé1",
+        ] {
+            assert!(parse_date(invalid).is_none(), "{:?}", invalid);
+        }
+    }
+}
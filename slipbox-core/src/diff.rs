@@ -0,0 +1,155 @@
+//! Comparing two states of a vault, e.g. before and after syncing it.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::{Note, State};
+
+/// The changes between two states of a vault, see [`State::diff`]. All lists are in path order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VaultDiff {
+    /// Notes that only exist in the newer state.
+    pub added: Vec<PathBuf>,
+    /// Notes that only exist in the older state.
+    pub removed: Vec<PathBuf>,
+    /// Notes that exist in both states, but were edited in between.
+    pub modified: Vec<NoteChange>,
+}
+
+/// A note that was edited between two states of a vault.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteChange {
+    pub path: PathBuf,
+    /// Tags the note only has in the newer state.
+    pub added_tags: Vec<String>,
+    /// Tags the note only has in the older state.
+    pub removed_tags: Vec<String>,
+}
+
+impl NoteChange {
+    /// Whether the tags of the note changed, not only its content.
+    pub fn is_retagged(&self) -> bool {
+        !self.added_tags.is_empty() || !self.removed_tags.is_empty()
+    }
+}
+
+impl VaultDiff {
+    /// Whether both states contain the same notes, none of them edited.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    /// The modified notes whose tags changed.
+    pub fn retagged(&self) -> Vec<&NoteChange> {
+        self.modified
+            .iter()
+            .filter(|change| change.is_retagged())
+            .collect()
+    }
+}
+
+impl fmt::Display for VaultDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} added, {} removed, {} modified, {} retagged",
+            self.added.len(),
+            self.removed.len(),
+            self.modified.len(),
+            self.retagged().len()
+        )
+    }
+}
+
+impl State {
+    /// Compare this state with a newer `other` state of the same vault. Notes are matched by path,
+    /// a renamed note is removed under its old path and added under the new one. A note counts as
+    /// modified if its last edit time differs between the states.
+    pub fn diff(&self, other: &State) -> VaultDiff {
+        let before: HashMap<&PathBuf, &Note> =
+            self.notes.iter().map(|note| (&note.path, note)).collect();
+        let after: HashMap<&PathBuf, &Note> =
+            other.notes.iter().map(|note| (&note.path, note)).collect();
+
+        let mut diff = VaultDiff::default();
+        for note in &other.notes {
+            match before.get(&note.path) {
+                None => diff.added.push(note.path.clone()),
+                Some(old) if old.last_edited != note.last_edited => {
+                    let missing_from = |a: &Note, b: &Note| {
+                        a.tags
+                            .iter()
+                            .filter(|tag| !b.tags.contains(tag))
+                            .cloned()
+                            .collect()
+                    };
+                    diff.modified.push(NoteChange {
+                        path: note.path.clone(),
+                        added_tags: missing_from(note, old),
+                        removed_tags: missing_from(old, note),
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = self
+            .notes
+            .iter()
+            .filter(|note| !after.contains_key(&note.path))
+            .map(|note| note.path.clone())
+            .collect();
+
+        // Both lists of notes are ordered by path already, unless they were changed by hand.
+        diff.added.sort();
+        diff.removed.sort();
+        diff.modified.sort_by(|a, b| a.path.cmp(&b.path));
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_diff() {
+        let mut before = State::from_sources(&[
+            ("A", "---\ntags: a b\n---\n"),
+            ("B", "---\ntags: b\n---\n"),
+            ("C", "---\ntags: c\n---\n"),
+            ("D", "---\ntags: d\n---\n"),
+        ]);
+        let mut after = State::from_sources(&[
+            ("A", "---\ntags: a c\n---\n"),
+            ("B", "---\ntags: b\n---\nEdited."),
+            ("C", "---\ntags: changed\n---\n"),
+            ("E", "---\ntags: e\n---\n"),
+        ]);
+        for (i, note) in before.notes.iter_mut().enumerate() {
+            note.last_edited = Some(UNIX_EPOCH + Duration::from_secs(i as u64));
+        }
+        for (i, note) in after.notes.iter_mut().enumerate() {
+            note.last_edited = Some(UNIX_EPOCH + Duration::from_secs(i as u64 + 1));
+        }
+        // C changed on disk, but wasn't edited since.
+        after.notes[2].last_edited = before.notes[2].last_edited;
+
+        let diff = before.diff(&after);
+        let name = |path: &PathBuf| path.file_stem().unwrap().to_string_lossy().into_owned();
+        assert!(diff.added.iter().map(name).collect::<Vec<_>>() == ["E"]);
+        assert!(diff.removed.iter().map(name).collect::<Vec<_>>() == ["D"]);
+        let modified = diff
+            .modified
+            .iter()
+            .map(|c| name(&c.path))
+            .collect::<Vec<_>>();
+        assert!(modified == ["A", "B"]);
+        assert!(diff.modified[0].added_tags == ["c"] && diff.modified[0].removed_tags == ["b"]);
+        assert!(!diff.modified[1].is_retagged());
+        assert!(diff.to_string() == "1 added, 1 removed, 2 modified, 1 retagged");
+
+        assert!(before.diff(&before).is_empty());
+    }
+}
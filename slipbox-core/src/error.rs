@@ -1,22 +1,156 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
 pub type Result<T> = core::result::Result<T, Error>;
 
 #[derive(Debug)]
 pub enum Error {
-    /// Unimplemented error enum for development.
-    Todo,
-
     /// Wrapper for std's IO error.
     StdIo(String),
+
+    /// Wrapper for std's IO error that occurred while accessing the file or directory at the path.
+    FileIo(PathBuf, String),
     
-    /// The path to a vault might be invalid.
-    InvalidPath,
+    /// The path to a vault is invalid, with a description of why.
+    InvalidPath(PathBuf, String),
     
-    /// Metadata parsing of the note was not successful.
-    MetaDataError(String),
+    /// The config file at the given path is invalid, with a description of why.
+    ConfigError(PathBuf, String),
+
+    /// Metadata parsing of the note at the given path was not successful.
+    MetaDataError(PathBuf, String),
+
+    /// A line of the metadata block of the note at the given path can't be parsed. The line is
+    /// counted from 1 at the top of the file, so editors can point at it.
+    MalformedMetaData(PathBuf, usize, String),
+
+    /// Serializing the vault failed.
+    Serialization(String),
+
+    /// A note at the given path has a `[[wiki link]]` to a target that doesn't exist in the vault.
+    BrokenLink(PathBuf, String),
+
+    /// A note at the given path has a markdown link like `[text](Other.md)` to a note file that
+    /// doesn't exist in the vault.
+    BrokenFileLink(PathBuf, String),
+
+    /// More than one note in the vault uses the given name.
+    DuplicateNoteName(String),
+
+    /// More than one note in the vault uses the given id.
+    DuplicateNoteId(String),
+
+    /// The alias is used by more than one note or is the name of another note.
+    DuplicateAlias(String),
+
+    /// The name can't be used as the file name of a note.
+    InvalidNoteName(String),
+
+    /// A note already exists at the given path.
+    NoteExists(PathBuf),
+
+    /// The template with the given name can't be used, with a description of why.
+    TemplateError(String, String),
+
+    /// The archive at the given path can't be read as a vault, with a description of why.
+    ArchiveError(PathBuf, String),
+
+    /// There is no note at the given path in the vault.
+    NoteNotFound(PathBuf),
+
+    /// The vault doesn't contain any notes.
+    EmptyVault,
+}
+
+impl Error {
+    /// Attach the path that was being accessed to an IO error.
+    pub(crate) fn io(path: &Path, e: std::io::Error) -> Self {
+        Self::FileIo(path.to_path_buf(), format!("{:?}", e))
+    }
+
+    /// The line of the note the error was found at, if it's known.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Self::MalformedMetaData(_, line, _) => Some(*line),
+            _ => None,
+        }
+    }
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StdIo(msg) => write!(f, "IO error: {}", msg),
+            Self::FileIo(path, msg) => write!(f, "IO error at {}: {}", path.display(), msg),
+            Self::InvalidPath(path, msg) => {
+                write!(f, "invalid vault path {}: {}", path.display(), msg)
+            }
+            Self::ConfigError(path, msg) => {
+                write!(f, "invalid config file {}: {}", path.display(), msg)
+            }
+            Self::MetaDataError(path, msg) => {
+                write!(f, "invalid metadata in {}: {}", path.display(), msg)
+            }
+            Self::MalformedMetaData(path, line, msg) => {
+                let path = path.display();
+                write!(f, "invalid metadata in {}, line {}: {}", path, line, msg)
+            }
+            Self::Serialization(msg) => write!(f, "serialization failed: {}", msg),
+            Self::BrokenLink(path, target) => {
+                write!(f, "broken link in {}: [[{}]]", path.display(), target)
+            }
+            Self::BrokenFileLink(path, target) => {
+                write!(f, "broken link in {}: ({})", path.display(), target)
+            }
+            Self::DuplicateNoteName(name) => write!(f, "duplicate note name: {}", name),
+            Self::DuplicateNoteId(id) => write!(f, "duplicate note id: {}", id),
+            Self::DuplicateAlias(alias) => write!(f, "duplicate note alias: {}", alias),
+            Self::InvalidNoteName(name) => write!(f, "invalid note name: {:?}", name),
+            Self::NoteExists(path) => write!(f, "note already exists: {}", path.display()),
+            Self::TemplateError(name, msg) => write!(f, "invalid template {}: {}", name, msg),
+            Self::ArchiveError(path, msg) => {
+                write!(f, "invalid archive {}: {}", path.display(), msg)
+            }
+            Self::NoteNotFound(path) => write!(f, "no such note: {}", path.display()),
+            Self::EmptyVault => write!(f, "the vault doesn't contain any notes"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Self {
         Self::StdIo(format!("{:?}", e))
     }
 }
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Serialization(format!("{:?}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let e = Error::MetaDataError(PathBuf::from("vault/Note.md"), String::from("no tags"));
+        assert!(e.to_string() == "invalid metadata in vault/Note.md: no tags");
+        assert!(e.line().is_none());
+
+        let e = Error::MalformedMetaData(PathBuf::from("Note.md"), 3, String::from("bad"));
+        assert!(e.to_string() == "invalid metadata in Note.md, line 3: bad");
+        assert!(e.line() == Some(3));
+
+        let e = Error::BrokenFileLink(PathBuf::from("Note.md"), String::from("../Gone.md"));
+        assert!(e.to_string() == "broken link in Note.md: (../Gone.md)");
+
+        let e = Error::InvalidPath(PathBuf::from("notes"), String::from("not found"));
+        let boxed: Box<dyn std::error::Error> = Box::new(e);
+        assert!(boxed.to_string() == "invalid vault path notes: not found");
+    }
+}
@@ -0,0 +1,420 @@
+//! Queries and exports operating on the link graph between notes.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use crate::{Note, State};
+
+/// Colors used for the nodes of the DOT graph when coloring by tag.
+const DOT_PALETTE: &[&str] = &[
+    "lightblue",
+    "lightcoral",
+    "palegreen",
+    "khaki",
+    "plum",
+    "lightsalmon",
+    "paleturquoise",
+    "pink",
+];
+
+/// The link graph between notes in a shape that can be fed straight into graph visualizations.
+/// Every note is a node, including orphans without any links.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Graph<'a> {
+    pub nodes: Vec<GraphNode<'a>>,
+    pub edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GraphNode<'a> {
+    /// Index of the note in [`State::notes`].
+    pub id: usize,
+    pub name: &'a str,
+    pub tags: &'a [String],
+}
+
+/// A directed edge between the nodes with the ids `source` and `target`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GraphEdge {
+    pub source: usize,
+    pub target: usize,
+}
+
+/// Escape a string so it can be used inside a double-quoted DOT identifier.
+fn escape_dot(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl State {
+    /// Index of every note by its path, used to turn links into graph edges.
+    pub(crate) fn _indices_by_path(&self) -> HashMap<&PathBuf, usize> {
+        self.notes
+            .iter()
+            .enumerate()
+            .map(|(i, note)| (&note.path, i))
+            .collect()
+    }
+
+    /// Returns all notes that neither link to another note nor are linked from another note. Links
+    /// of a note to itself don't connect it to the graph, so such notes are orphans as well.
+    pub fn orphans(&self) -> Vec<&Note> {
+        self.notes
+            .iter()
+            .filter(|note| self._is_orphan(note))
+            .collect()
+    }
+
+    pub(crate) fn _is_orphan(&self, note: &Note) -> bool {
+        note.links.iter().all(|link| *link == note.path)
+            && self
+                .backlinks(note)
+                .iter()
+                .all(|other| other.path == note.path)
+    }
+
+    /// The `n` notes with the most links, counting both the links of a note and the links to it, as
+    /// hubs of the vault like index notes. Notes with the same number of links are ordered by
+    /// path.
+    pub fn most_linked(&self, n: usize) -> Vec<(&Note, usize)> {
+        let mut degrees = self
+            .notes
+            .iter()
+            .map(|note| (note, note.links.len() + self.backlinks(note).len()))
+            .collect::<Vec<_>>();
+        degrees.sort_by(|(a, a_degree), (b, b_degree)| {
+            b_degree.cmp(a_degree).then_with(|| a.path.cmp(&b.path))
+        });
+        degrees.truncate(n);
+        degrees
+    }
+
+    /// The shortest chain of `[[links]]` leading from `from` to `to`, including both notes, or
+    /// `None` if `to` can't be reached by following links. Links are directed, so the path back
+    /// may differ or not exist at all.
+    pub fn path_between(&self, from: &Note, to: &Note) -> Option<Vec<&Note>> {
+        let indices = self._indices_by_path();
+        let (start, end) = (*indices.get(&from.path)?, *indices.get(&to.path)?);
+        let reached = self._breadth_first(start, &indices);
+        if !reached.contains_key(&end) {
+            return None;
+        }
+
+        let mut path = vec![&self.notes[end]];
+        let mut current = end;
+        while let Some(&(_, Some(before))) = reached.get(&current) {
+            path.push(&self.notes[before]);
+            current = before;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// All notes reachable from `note` by following `[[links]]`, starting with the note itself and
+    /// ordered by the number of links needed to reach them, then by their position in
+    /// [`State::notes`].
+    pub fn connected_component(&self, note: &Note) -> Vec<&Note> {
+        let indices = self._indices_by_path();
+        let start = match indices.get(&note.path) {
+            Some(&start) => start,
+            None => return vec![],
+        };
+        let mut reached = self
+            ._breadth_first(start, &indices)
+            .into_iter()
+            .collect::<Vec<_>>();
+        reached.sort_by_key(|&(i, (depth, _))| (depth, i));
+        reached.into_iter().map(|(i, _)| &self.notes[i]).collect()
+    }
+
+    /// Link cycles like `A → B → C → A`, each listed from the note the cycle was entered at and
+    /// without repeating it at the end. A note linking to itself is a cycle of length 1. The
+    /// cycles are found by a depth-first search from every note in path order, with one cycle per
+    /// link leading back to a note on the current search path. Every link that is part of a cycle
+    /// is covered, but not every possible cycle through the same notes is listed.
+    pub fn cycles(&self) -> Vec<Vec<&Note>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Visit {
+            New,
+            OnPath,
+            Done,
+        }
+
+        let indices = self._indices_by_path();
+        let mut visits = vec![Visit::New; self.notes.len()];
+        let mut cycles = vec![];
+        for start in 0..self.notes.len() {
+            if visits[start] != Visit::New {
+                continue;
+            }
+            // The current search path, with the number of links of each note already followed.
+            let mut path = vec![(start, 0)];
+            visits[start] = Visit::OnPath;
+            while let Some((current, next_link)) = path.last_mut() {
+                let current = *current;
+                let link = self.notes[current].links.get(*next_link);
+                *next_link += 1;
+                let next = match link {
+                    Some(link) => match indices.get(link) {
+                        Some(&next) => next,
+                        None => continue,
+                    },
+                    None => {
+                        visits[current] = Visit::Done;
+                        path.pop();
+                        continue;
+                    }
+                };
+                match visits[next] {
+                    Visit::New => {
+                        visits[next] = Visit::OnPath;
+                        path.push((next, 0));
+                    }
+                    Visit::OnPath => {
+                        let entry = path.iter().position(|&(i, _)| i == next).unwrap_or(0);
+                        cycles.push(path[entry..].iter().map(|&(i, _)| &self.notes[i]).collect());
+                    }
+                    Visit::Done => {}
+                }
+            }
+        }
+        cycles
+    }
+
+    /// Visit the link graph breadth first from the note at index `start`. Maps the index of every
+    /// reached note to the number of links needed to reach it and the note it was reached from.
+    fn _breadth_first(
+        &self,
+        start: usize,
+        indices: &HashMap<&PathBuf, usize>,
+    ) -> HashMap<usize, (usize, Option<usize>)> {
+        let mut reached = HashMap::from([(start, (0, None))]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(current) = queue.pop_front() {
+            let depth = reached[&current].0;
+            for link in &self.notes[current].links {
+                if let Some(&next) = indices.get(link) {
+                    reached.entry(next).or_insert_with(|| {
+                        queue.push_back(next);
+                        (depth + 1, Some(current))
+                    });
+                }
+            }
+        }
+        reached
+    }
+
+    /// Export the link graph as a GraphViz DOT digraph, with one node per note labeled by its name
+    /// and one edge per `[[link]]`.
+    pub fn to_dot(&self) -> String {
+        self._to_dot(false)
+    }
+
+    /// Like [`State::to_dot`], but fills each node with a color picked by the note's first tag.
+    pub fn to_dot_by_tag(&self) -> String {
+        self._to_dot(true)
+    }
+
+    /// Build the nodes and edges of the link graph.
+    pub fn to_graph(&self) -> Graph<'_> {
+        let indices = self._indices_by_path();
+        let nodes = self
+            .notes
+            .iter()
+            .enumerate()
+            .map(|(id, note)| GraphNode {
+                id,
+                name: &note.name,
+                tags: &note.tags,
+            })
+            .collect();
+        let edges = self
+            .notes
+            .iter()
+            .enumerate()
+            .flat_map(|(source, note)| {
+                note.links
+                    .iter()
+                    .filter_map(|link| indices.get(link))
+                    .map(move |&target| GraphEdge { source, target })
+            })
+            .collect();
+
+        Graph { nodes, edges }
+    }
+
+    /// Serialize the link graph built by [`State::to_graph`] to JSON.
+    #[cfg(feature = "serde")]
+    pub fn to_graph_json(&self) -> crate::Result<String> {
+        Ok(serde_json::to_string(&self.to_graph())?)
+    }
+
+    fn _to_dot(&self, color_by_tag: bool) -> String {
+        let indices = self._indices_by_path();
+        let mut colors: HashMap<&str, &str> = HashMap::new();
+
+        // Writing into a String can't fail, so the results of `write!` are ignored.
+        let mut dot = String::from("digraph vault {\n");
+        for (i, note) in self.notes.iter().enumerate() {
+            let _ = write!(dot, "    n{} [label=\"{}\"", i, escape_dot(&note.name));
+            if let (true, Some(tag)) = (color_by_tag, note.tags.first()) {
+                let next = DOT_PALETTE[colors.len() % DOT_PALETTE.len()];
+                let color = *colors.entry(tag.as_str()).or_insert(next);
+                let _ = write!(dot, ", style=filled, fillcolor=\"{}\"", color);
+            }
+            dot.push_str("];\n");
+        }
+        for (i, note) in self.notes.iter().enumerate() {
+            for link in &note.links {
+                if let Some(target) = indices.get(link) {
+                    let _ = writeln!(dot, "    n{} -> n{};", i, target);
+                }
+            }
+        }
+        dot.push_str("}\n");
+
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_vault;
+    use crate::Result;
+
+    #[test]
+    fn test_dot() -> Result<()> {
+        let state = State::new(PathBuf::from("./tests/vault"))?;
+        let dot = state.to_dot();
+
+        assert!(dot.starts_with("digraph vault {\n"));
+        assert!(dot.contains("    n0 [label=\"TestNote01\"];\n"));
+        assert!(dot.contains("    n0 -> n1;\n    n0 -> n3;\n"));
+        assert!(state
+            .to_dot_by_tag()
+            .contains("n1 [label=\"TestNote02\", style=filled"));
+        assert!(escape_dot("say \"hi\"\\") == "say \\\"hi\\\"\\\\");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_orphans() -> Result<()> {
+        let state = State::new(PathBuf::from("./tests/vault"))?;
+        let orphans = state.orphans();
+        assert!(orphans.len() == 1 && orphans[0].name == "TestNote03");
+
+        let path = scratch_vault("orphans");
+        std::fs::write(path.join("Loop.md"), "[[Loop]]")?;
+        let state = State::new(path.clone())?;
+        assert!(state.notes[0].links.len() == 1 && state.orphans().len() == 1);
+
+        std::fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_cycles() -> Result<()> {
+        let state = State::from_sources(&[
+            ("A", "[[B]]"),
+            ("B", "[[C]] [[D]]"),
+            ("C", "[[A]]"),
+            ("D", "[[D]] [[E]]"),
+            ("E", "[[B]]"),
+            ("F", "[[A]]"),
+        ]);
+        let cycles = state
+            .cycles()
+            .into_iter()
+            .map(|cycle| {
+                cycle
+                    .iter()
+                    .map(|note| note.name.as_str())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert!(cycles == [vec!["A", "B", "C"], vec!["D"], vec!["B", "D", "E"]]);
+
+        let state = State::new(PathBuf::from("./tests/vault"))?;
+        assert!(state.cycles().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_most_linked() -> Result<()> {
+        let state = State::new(PathBuf::from("./tests/vault"))?;
+        let hubs = state
+            .most_linked(2)
+            .into_iter()
+            .map(|(note, degree)| (note.name.as_str(), degree))
+            .collect::<Vec<_>>();
+        assert!(hubs == [("TestNote01", 2), ("TestNote02", 1)]);
+        assert!(state.most_linked(10).len() == 4);
+        assert!(state.most_linked(0).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_paths() -> Result<()> {
+        let state = State::from_sources(&[
+            ("A", "[[B]] [[C]]"),
+            ("B", "[[D]]"),
+            ("C", "[[D]] [[E]]"),
+            ("D", "[[A]]"),
+            ("E", "[[F]]"),
+            ("F", ""),
+            ("Island", "[[Island]]"),
+        ]);
+        let note = |name| state.note_by_name(name).unwrap();
+        let names = |notes: Vec<&Note>| {
+            notes
+                .into_iter()
+                .map(|n| n.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(names(state.path_between(note("A"), note("F")).unwrap()) == ["A", "C", "E", "F"]);
+        assert!(state.path_between(note("E"), note("B")).is_none());
+        assert!(names(state.path_between(note("D"), note("D")).unwrap()) == ["D"]);
+        assert!(state.path_between(note("A"), note("Island")).is_none());
+
+        let component = names(state.connected_component(note("B")));
+        assert!(component == ["B", "D", "A", "C", "E", "F"]);
+        assert!(names(state.connected_component(note("Island"))) == ["Island"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_graph() -> Result<()> {
+        let state = State::new(PathBuf::from("./tests/vault"))?;
+        let graph = state.to_graph();
+
+        assert!(graph.nodes.len() == 4);
+        assert!(graph.nodes[2].name == "TestNote03" && graph.nodes[2].tags == ["a", "cool"]);
+        let edges = graph
+            .edges
+            .iter()
+            .map(|edge| (edge.source, edge.target))
+            .collect::<Vec<_>>();
+        assert!(edges == [(0, 1), (0, 3)]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+//! Excluding paths from a vault through a gitignore-like [`IGNORE_FILE`] at its root.
+
+use std::fs;
+use std::path::{Component, Path};
+
+use crate::{Error, Result};
+
+/// Name of the optional file at the root of a vault listing paths that aren't part of the vault.
+///
+/// Every line is a glob pattern relative to the vault root, blank lines and lines starting with
+/// `#` are ignored. `*` matches anything except `/`, `?` a single character except `/` and `**`
+/// any number of directories. A pattern ending in `/` only matches directories. Patterns without
+/// a `/` in them match files and directories of that name at any depth, e.g. `templates/` skips
+/// every directory called `templates` while `/templates/` only skips the one at the root.
+pub const IGNORE_FILE: &str = ".slipboxignore";
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    glob: Vec<char>,
+    /// Matched against the whole path relative to the root instead of only the file name.
+    anchored: bool,
+    dir_only: bool,
+}
+
+/// The patterns of an [`IGNORE_FILE`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IgnorePatterns {
+    patterns: Vec<Pattern>,
+}
+
+impl IgnorePatterns {
+    /// Read the [`IGNORE_FILE`] at the root of a vault. No patterns if there is none.
+    pub(crate) fn load(root: &Path) -> Result<Self> {
+        let path = root.join(IGNORE_FILE);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(&path).map_err(|e| Error::io(&path, e))?;
+        Ok(Self::parse(&text))
+    }
+
+    pub(crate) fn parse(text: &str) -> Self {
+        let patterns = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (line, dir_only) = match line.strip_suffix('/') {
+                    Some(line) => (line, true),
+                    None => (line, false),
+                };
+                let anchored = line.contains('/');
+                Pattern {
+                    glob: line.trim_start_matches('/').chars().collect(),
+                    anchored,
+                    dir_only,
+                }
+            })
+            .collect();
+        Self { patterns }
+    }
+
+    /// Whether the path, relative to the vault root, matches any of the patterns.
+    pub(crate) fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        let components = relative
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let full = components.join("/").chars().collect::<Vec<_>>();
+        let name = match components.last() {
+            Some(name) => name.chars().collect::<Vec<_>>(),
+            None => return false,
+        };
+
+        self.patterns.iter().any(|pattern| {
+            (is_dir || !pattern.dir_only)
+                && match pattern.anchored {
+                    true => glob_match(&pattern.glob, &full),
+                    false => glob_match(&pattern.glob, &name),
+                }
+        })
+    }
+}
+
+/// Match `text` against a glob `pattern`, see [`IGNORE_FILE`] for the syntax.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // Zero or more whole directories.
+            glob_match(rest, text)
+                || (0..text.len()).any(|i| text[i] == '/' && glob_match(rest, &text[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != '/')
+            .any(|i| glob_match(rest, &text[i..])),
+        ['?', rest @ ..] => match text {
+            [c, text @ ..] if *c != '/' => glob_match(rest, text),
+            _ => false,
+        },
+        [p, rest @ ..] => match text {
+            [c, text @ ..] if c == p => glob_match(rest, text),
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        let matches = |pattern: &str, text: &str| {
+            glob_match(
+                &pattern.chars().collect::<Vec<_>>(),
+                &text.chars().collect::<Vec<_>>(),
+            )
+        };
+        assert!(matches("*.md", "note.md") && !matches("*.md", "dir/note.md"));
+        assert!(matches("note?.md", "note1.md") && !matches("note?.md", "note.md"));
+        assert!(matches("archive/**/*.md", "archive/note.md"));
+        assert!(matches("archive/**/*.md", "archive/2023/01/note.md"));
+        assert!(matches("archive/**", "archive/2023/note.md"));
+        assert!(matches("**/drafts", "a/b/drafts") && matches("**/drafts", "drafts"));
+        assert!(!matches("archive/*", "archive/2023/note.md"));
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        let ignore = IgnorePatterns::parse("# comment\n\ntemplates/\n/attachments\n*.draft.md\n");
+        assert!(ignore.is_ignored(Path::new("templates"), true));
+        assert!(ignore.is_ignored(Path::new("a/templates"), true));
+        assert!(!ignore.is_ignored(Path::new("templates"), false));
+        assert!(ignore.is_ignored(Path::new("attachments"), true));
+        assert!(!ignore.is_ignored(Path::new("a/attachments"), true));
+        assert!(ignore.is_ignored(Path::new("a/b/idea.draft.md"), false));
+        assert!(!ignore.is_ignored(Path::new("a/b/idea.md"), false));
+    }
+}
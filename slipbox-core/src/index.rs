@@ -0,0 +1,219 @@
+//! Caching a parsed vault in a JSON index file, enabled by the `serde` feature.
+//!
+//! Loading a vault from its index only re-parses the notes that changed since the index was
+//! written, so large vaults are available right away.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{Config, Error, FrontmatterKind, Note, Result, State, Vault};
+
+/// Version of the index format, indices of other versions are rejected.
+const INDEX_VERSION: u32 = 4;
+
+/// A point in time as seconds and nanoseconds since the unix epoch. Full precision is needed to
+/// tell whether a file was modified after the index was written.
+type Timestamp = (u64, u32);
+
+fn to_timestamp(time: Option<SystemTime>) -> Option<Timestamp> {
+    let since_epoch = time?.duration_since(UNIX_EPOCH).ok()?;
+    Some((since_epoch.as_secs(), since_epoch.subsec_nanos()))
+}
+
+fn from_timestamp(timestamp: Option<Timestamp>) -> Option<SystemTime> {
+    timestamp.map(|(secs, nanos)| UNIX_EPOCH + Duration::new(secs, nanos))
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    /// Relative to the vault root.
+    path: PathBuf,
+    name: String,
+    id: String,
+    tags: Vec<String>,
+    display_tags: Vec<String>,
+    frontmatter: HashMap<String, String>,
+    raw_frontmatter: Option<String>,
+    frontmatter_kind: Option<FrontmatterKind>,
+    aliases: Vec<String>,
+    draft: bool,
+    created_on: Option<Timestamp>,
+    last_edited: Option<Timestamp>,
+    content_hash: Option<u64>,
+    raw_links: Vec<String>,
+    file_links: Vec<String>,
+    external_links: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Index {
+    version: u32,
+    vault_path: PathBuf,
+    name: String,
+    config: Config,
+    notes: Vec<IndexEntry>,
+}
+
+impl Vault {
+    /// Write all parsed notes of the vault to a JSON index file at `path`, to be loaded again with
+    /// [`Vault::from_index`].
+    pub fn write_index(&self, path: &Path) -> Result<()> {
+        let root = self.state.path.as_path();
+        let notes = self
+            .state
+            .iter()
+            .map(|note| IndexEntry {
+                path: note
+                    .path
+                    .strip_prefix(root)
+                    .unwrap_or(&note.path)
+                    .to_path_buf(),
+                name: note.name.clone(),
+                id: note.id.clone(),
+                tags: note.tags.clone(),
+                display_tags: note.display_tags.clone(),
+                frontmatter: note.frontmatter.clone(),
+                raw_frontmatter: note.raw_frontmatter.clone(),
+                frontmatter_kind: note.frontmatter_kind,
+                aliases: note.aliases.clone(),
+                draft: note.draft,
+                created_on: to_timestamp(note.created_on),
+                last_edited: to_timestamp(note.last_edited),
+                content_hash: note.content_hash,
+                raw_links: note.raw_links.clone(),
+                file_links: note.file_links.clone(),
+                external_links: note.external_links.clone(),
+            })
+            .collect();
+        let index = Index {
+            version: INDEX_VERSION,
+            vault_path: self.vault_path.clone(),
+            name: self.name.clone(),
+            config: self.state.config.clone(),
+            notes,
+        };
+
+        let json = serde_json::to_string(&index)?;
+        fs::write(path, json).map_err(|e| Error::io(path, e))
+    }
+
+    /// Load a vault from an index file written by [`Vault::write_index`]. Notes whose file was
+    /// modified since the index was written are stale and parsed again, notes that were deleted
+    /// are dropped and new ones added, just like [`State::refresh`] does. Returns the vault
+    /// together with the paths of all stale notes.
+    pub fn from_index(path: &Path) -> Result<(Self, Vec<PathBuf>)> {
+        let json = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let index: Index = serde_json::from_str(&json)?;
+        if index.version != INDEX_VERSION {
+            return Err(Error::Serialization(format!(
+                "unsupported index version {}, expected {}",
+                index.version, INDEX_VERSION
+            )));
+        }
+        Self::_check_path(&index.vault_path)?;
+
+        let root = index.vault_path.clone();
+        let mut state = State::_empty(root.clone(), index.config);
+        state.notes = index
+            .notes
+            .into_iter()
+            .map(|entry| Note {
+                path: root.join(&entry.path),
+                name: entry.name,
+                id: entry.id,
+                tags: entry.tags,
+                display_tags: entry.display_tags,
+                frontmatter: entry.frontmatter,
+                raw_frontmatter: entry.raw_frontmatter,
+                frontmatter_kind: entry.frontmatter_kind,
+                aliases: entry.aliases,
+                draft: entry.draft,
+                created_on: from_timestamp(entry.created_on),
+                last_edited: from_timestamp(entry.last_edited),
+                content_hash: entry.content_hash,
+                links: vec![],
+                raw_links: entry.raw_links,
+                file_links: entry.file_links,
+                external_links: entry.external_links,
+                cached_content: None,
+            })
+            .collect();
+
+        // The index doesn't hold the contents, so they have to be read for the search index.
+        #[cfg(feature = "search")]
+        for note in &state.notes {
+            if let Ok(contents) = fs::read_to_string(&note.path) {
+                let terms = crate::search::term_counts(&contents);
+                state.terms.insert(note.path.clone(), terms);
+            }
+        }
+        if state.config.cache_content {
+            for note in state.notes.iter_mut() {
+                note.cached_content = fs::read_to_string(&note.path).ok();
+            }
+        }
+
+        let stale = state.refresh()?;
+        info!("loaded vault from index, {} stale notes", stale.len());
+        let vault = Self {
+            created_on: Self::_created_on(&index.vault_path),
+            vault_path: index.vault_path,
+            name: index.name,
+            state,
+            templates: HashMap::new(),
+        };
+        Ok((vault, stale))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_vault;
+
+    #[test]
+    fn test_index() -> Result<()> {
+        let path = scratch_vault("index");
+        let vault_path = path.join("vault");
+        fs::create_dir_all(&vault_path)?;
+        fs::write(
+            vault_path.join("A.md"),
+            "---\ntags: A b\naliases: [first]\n---\n[[B]]",
+        )?;
+        fs::write(vault_path.join("B.md"), "---\ntags: b\n---\n[[first]]")?;
+        fs::write(vault_path.join("C.md"), "---\ntags: c\n---\n")?;
+
+        let vault = Vault::new(vault_path.clone())?;
+        vault.write_index(&path.join("index.json"))?;
+
+        let (indexed, stale) = Vault::from_index(&path.join("index.json"))?;
+        assert!(stale.is_empty());
+        assert!(indexed.name == "vault" && indexed.state.len() == 3);
+        for (a, b) in vault.state.iter().zip(indexed.state.iter()) {
+            assert!(a.path == b.path && a.display_tags == b.display_tags && a.links == b.links);
+            assert!(a.last_edited == b.last_edited && a.aliases == b.aliases);
+        }
+
+        // Make sure the modification time differs even on coarse file systems.
+        std::thread::sleep(Duration::from_millis(20));
+        fs::write(vault_path.join("B.md"), "---\ntags: changed\n---\n")?;
+        fs::remove_file(vault_path.join("C.md"))?;
+        let (indexed, stale) = Vault::from_index(&path.join("index.json"))?;
+        assert!(stale == [vault_path.join("B.md"), vault_path.join("C.md")]);
+        assert!(indexed.state.len() == 2 && indexed.state.notes[1].tags == ["changed"]);
+
+        fs::write(path.join("index.json"), "{\"version\": 1}")?;
+        assert!(matches!(
+            Vault::from_index(&path.join("index.json")),
+            Err(Error::Serialization(_))
+        ));
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+}
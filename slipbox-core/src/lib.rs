@@ -1,19 +1,75 @@
-use std::collections::HashSet;
-use std::{fs, path::PathBuf};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 
 use std::time::SystemTime;
 
-use std::io::Read;
+use std::io::{Read, Write};
 
 use pulldown_cmark::{
-    CowStr, Event, MetadataBlockKind, Parser, Tag::MetadataBlock, TextMergeStream,
+    Event, MetadataBlockKind, Parser, Tag, Tag::MetadataBlock, TagEnd, TextMergeStream,
 };
 
-use tracing::info;
+use tracing::level_filters::LevelFilter;
+use tracing::{debug, info, warn};
 
+use self::ignore::IgnorePatterns;
+
+#[cfg(feature = "archive")]
+mod archive;
+mod builder;
+mod config;
+mod date;
+mod diff;
 mod error;
+mod graph;
+mod ignore;
+#[cfg(feature = "serde")]
+mod index;
+#[cfg(feature = "async")]
+mod nonblocking;
+mod query;
+mod render;
+#[cfg(feature = "search")]
+mod search;
+#[cfg(feature = "serde")]
+mod serialize;
+mod stats;
+mod tags;
+mod template;
+mod validate;
+mod walk;
+#[cfg(feature = "watcher")]
+mod watcher;
+mod workspace;
+pub use self::builder::VaultBuilder;
+pub use self::config::{Config, CONFIG_FILE, DEFAULT_TAG_KEY, TAGS_FILE, TEMPLATES_DIR};
+pub use self::diff::{NoteChange, VaultDiff};
 pub use self::error::{Error, Result};
+pub use self::graph::{Graph, GraphEdge, GraphNode};
+pub use self::ignore::IGNORE_FILE;
+#[cfg(feature = "async")]
+pub use self::nonblocking::LoadVault;
+pub use self::query::{SortKey, SortOrder};
+pub use self::render::WikiLinks;
+pub use self::stats::VaultStats;
+pub use self::tags::{is_valid_tag, MatchMode, TagNode, UNTAGGED};
+pub use self::validate::{IssueCategory, ValidationIssue};
+pub use self::workspace::Workspace;
 
+/// The syntax of the metadata block of a note, see [`Note::frontmatter_kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrontmatterKind {
+    /// YAML style `key: value` lines between `---` delimiters.
+    Yaml,
+    /// TOML style `key = value` lines between `+++` delimiters.
+    Toml,
+    /// The `#+KEY: value` header of an Org-mode note.
+    Org,
+}
 
 /// The "atomic" Note is a markdown file that contains the contents which make up the note.
 /// By default, each note starts off as a draft and can be set to finished manually. The purpose of
@@ -21,216 +77,3219 @@ pub use self::error::{Error, Result};
 #[derive(Debug, Clone)]
 pub struct Note {
     pub name: String,
+    /// Where the note was found in the vault. For a symlinked note this is the path of the link,
+    /// not of the file it points to.
     pub path: PathBuf,
+    /// The tags of the note, normalized to lowercase so that matching is case-insensitive. The
+    /// tags keep the order they are declared in, so the first one is the primary tag of the note.
     pub tags: Vec<String>,
-    // pub id: String,
-    // pub draft: bool,
-    // pub created_on: SystemTime,
-    // pub last_edited: SystemTime,
-    // pub links: Vec<Note>,
+    /// The tags as they are written in the note, in the same order as `tags`. Use these for display.
+    pub display_tags: Vec<String>,
+    /// All `key: value` fields of the metadata block, including the tags.
+    pub frontmatter: HashMap<String, String>,
+    /// The metadata block exactly as it's written in the note, delimiters included, e.g. to write
+    /// it back unchanged when editing the note. `None` if the note has no metadata block.
+    pub raw_frontmatter: Option<String>,
+    /// The syntax of the metadata block, so that edits can be written back in the same syntax.
+    /// `None` if the note has no metadata block.
+    pub frontmatter_kind: Option<FrontmatterKind>,
+    /// Alternative names of the note from an `aliases:` metadata field. `[[links]]` and
+    /// [`State::note_by_name`] can refer to a note by any of them.
+    pub aliases: Vec<String>,
+    /// Stable identifier of the note, taken from an `id:` metadata field or derived from the path
+    /// of the note relative to the vault root.
+    pub id: String,
+    /// Whether the note is still a draft, set through `draft: false` or `status: finished` in the
+    /// metadata block. Defaults to `true`.
+    pub draft: bool,
+    /// Creation time of the note, from the `created:` or `date:` field of its metadata block if it
+    /// has one, otherwise of the note file. `None` if the platform doesn't record it.
+    pub created_on: Option<SystemTime>,
+    /// Last modification time of the note file, for a symlinked note of the file it points to.
+    pub last_edited: Option<SystemTime>,
+    /// A 64 bit FNV-1a hash of the contents of the note file, the same on every platform and run.
+    /// Only computed with the `hash` feature, `None` otherwise.
+    pub content_hash: Option<u64>,
+    /// Paths of the notes this note links to via `[[wiki links]]` or relative markdown links like
+    /// `[text](Other.md)`.
+    pub links: Vec<PathBuf>,
+    /// The `[[wiki link]]` targets as they are written in the note, including ones that don't
+    /// resolve.
+    pub raw_links: Vec<String>,
+    /// The targets of markdown links to other notes, relative to the folder of the note and
+    /// without any `#heading`, e.g. `../projects/Rust.md`. Links to websites and to files that
+    /// aren't notes aren't included.
+    pub file_links: Vec<String>,
+    /// The `http://` and `https://` URLs the note links to, each listed once, e.g. for checking
+    /// for dead links.
+    pub external_links: Vec<String>,
+    /// The contents of the note file as read while loading, if [`Config::cache_content`] is set.
+    pub cached_content: Option<String>,
 }
 
-const TAG_IDENTIFIER: &str = "tags:";
+impl Note {
+    /// The full contents of the note file, including the metadata block. Served from the cache if
+    /// the content was kept while loading, otherwise read from disk.
+    pub fn content(&self) -> Result<String> {
+        match &self.cached_content {
+            Some(content) => Ok(content.clone()),
+            None => fs::read_to_string(&self.path).map_err(|e| Error::io(&self.path, e)),
+        }
+    }
+
+    /// The path of the note relative to the root of `vault`, e.g. `projects/Rust.md`, for display
+    /// independent of how the vault path was given.
+    pub fn relative_path(&self, vault: &Vault) -> PathBuf {
+        self.path
+            .strip_prefix(&vault.state.path)
+            .unwrap_or(&self.path)
+            .to_path_buf()
+    }
+
+    /// Whether the note carries `tag` or any tag nested below it, treating `/` as the hierarchy
+    /// separator. Tags are matched case-insensitively, so `Programming` matches `programming` and
+    /// `programming/rust/async`, but not `programming-languages`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = Self::_lowercase(tag);
+        self.tags.iter().any(|t| match t.strip_prefix(tag.as_ref()) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        })
+    }
+
+    /// Whether the note carries exactly `tag`, matched case-insensitively. Unlike
+    /// [`Note::has_tag`], tags nested below it don't count.
+    pub fn has_exact_tag(&self, tag: &str) -> bool {
+        let tag = Self::_lowercase(tag);
+        self.tags.iter().any(|t| *t == tag)
+    }
+
+    /// Tags are stored in lowercase, so queries are lowercased too, without allocating if they
+    /// already are.
+    fn _lowercase(tag: &str) -> Cow<'_, str> {
+        match tag.chars().any(char::is_uppercase) {
+            true => Cow::Owned(tag.to_lowercase()),
+            false => Cow::Borrowed(tag),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct State {
-    pub notes: Vec<Note>, // todo: Ideally we cache notes so that we only re-parse notes that have changed
+    pub notes: Vec<Note>,
+    /// Links that don't point to any note in the vault, see [`Error::BrokenLink`] and
+    /// [`Error::BrokenFileLink`].
+    pub link_errors: Vec<Error>,
+    /// Notes and directories that could not be loaded together with the reason why. They are
+    /// skipped instead of failing the whole vault.
+    pub errors: Vec<(PathBuf, Error)>,
+    /// Conflicts between notes, e.g. [`Error::DuplicateNoteId`]. The conflicting notes are still
+    /// loaded.
+    pub conflicts: Vec<Error>,
+    /// Reverse link index mapping a note path to the indices of all notes linking to it.
+    backlinks: HashMap<PathBuf, Vec<usize>>,
+    /// Root directory the notes are read from.
+    path: PathBuf,
+    config: Config,
+    /// Patterns of the vault's [`IGNORE_FILE`], re-read on every scan.
+    ignore: IgnorePatterns,
+    /// How often each word occurs in the body of a note, by note path.
+    #[cfg(feature = "search")]
+    terms: HashMap<PathBuf, HashMap<String, usize>>,
 }
 
 impl State {
-    pub fn new(path: PathBuf) -> Result<Self> {
-        let mut state = Self { notes: vec![] };
-        Self::_read_notes(&mut state, path)?;
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_config(path, Config::default())
+    }
+
+    pub fn with_config(path: impl AsRef<Path>, config: Config) -> Result<Self> {
+        let mut state = Self::_empty(path.as_ref().to_path_buf(), config);
+        Self::_read_notes(&mut state)?;
+        state._update_indices();
+        state._check_unique_names()?;
         Ok(state)
     }
 
-    pub fn tags(&self) -> HashSet<String> {
-        let mut tag_set: HashSet<String> = HashSet::new();
-        self.notes.clone().into_iter().for_each(|note| {
-            note.tags.into_iter().for_each(|tag| {
-                tag_set.insert(tag);
-            })
-        });
+    /// Build a state from notes that are already in memory instead of reading a vault directory,
+    /// e.g. for tests. Links between the notes are resolved as usual. The state isn't backed by a
+    /// directory, so [`State::refresh`] fails.
+    pub fn from_notes(notes: Vec<Note>) -> Self {
+        let mut state = Self::_empty(PathBuf::new(), Config::default());
+        for note in notes {
+            let contents = note.cached_content.clone().unwrap_or_default();
+            state._push_note(note.path.clone(), Ok((note, contents)));
+        }
+        state.notes.sort_by(|a, b| a.path.cmp(&b.path));
+        state._update_indices();
+        state
+    }
+
+    /// Build a state from `(name, contents)` pairs, parsing each one as if it was the file
+    /// `<name>.md`. The contents are kept, so [`Note::content`] works without any files. Notes that
+    /// fail to parse are recorded in `errors` like when loading a vault.
+    pub fn from_sources(sources: &[(&str, &str)]) -> Self {
+        let sources = sources
+            .iter()
+            .map(|(name, contents)| (PathBuf::from(format!("{}.md", name)), String::from(*contents)))
+            .collect();
+        Self::_from_contents(PathBuf::new(), Config::default(), sources)
+    }
+
+    /// Build a state from the paths and contents of notes that aren't read from the file system.
+    /// The contents are kept in [`Note::cached_content`].
+    pub(crate) fn _from_contents(
+        path: PathBuf,
+        config: Config,
+        sources: Vec<(PathBuf, String)>,
+    ) -> Self {
+        let mut state = Self::_empty(path, config);
+        for (path, contents) in sources {
+            let result = state._parse_note(path.clone(), &contents).map(|note| {
+                let note = Note {
+                    cached_content: Some(contents.clone()),
+                    ..note
+                };
+                (note, contents)
+            });
+            state._push_note(path, result);
+        }
+        state.notes.sort_by(|a, b| a.path.cmp(&b.path));
+        state._update_indices();
+        state
+    }
+
+    /// A state without any notes.
+    fn _empty(path: PathBuf, config: Config) -> Self {
+        Self {
+            notes: vec![],
+            link_errors: vec![],
+            errors: vec![],
+            conflicts: vec![],
+            backlinks: HashMap::new(),
+            path,
+            config,
+            ignore: IgnorePatterns::default(),
+            #[cfg(feature = "search")]
+            terms: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Whether any note failed to load. Strict callers can use this to treat a partially loaded
+    /// vault as an error.
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    /// Iterate over all loaded notes, ordered by path. Prefer this over accessing `notes` directly.
+    pub fn iter(&self) -> impl Iterator<Item = &Note> {
+        self.notes.iter()
+    }
+
+    /// Returns all notes for which `predicate` holds, in path order. The other filters like
+    /// [`State::drafts`] are shortcuts for common predicates.
+    pub fn filter<F: Fn(&Note) -> bool>(&self, predicate: F) -> Vec<&Note> {
+        self.notes.iter().filter(|note| predicate(note)).collect()
+    }
+
+    /// Number of loaded notes.
+    pub fn len(&self) -> usize {
+        self.notes.len()
+    }
+
+    /// Whether no notes were loaded.
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty()
+    }
+
+    /// Re-scan the vault directory and bring the notes up to date without re-parsing everything.
+    /// Only notes whose file was modified after it was last parsed are read again, notes whose file
+    /// disappeared are removed and newly created files are added. Returns the paths of all notes
+    /// that were added, re-parsed or removed. With the `hash` feature, a note whose file was
+    /// modified without changing its content, e.g. by a checkout, is re-parsed but not returned.
+    pub fn refresh(&mut self) -> Result<Vec<PathBuf>> {
+        let mut paths = vec![];
+        let skipped = self._scan(&mut paths)?;
+
+        let mut cached: HashMap<PathBuf, Note> = self
+            .notes
+            .drain(..)
+            .map(|note| (note.path.clone(), note))
+            .collect();
+
+        // Notes that failed before are re-read every time, but only count as changed once they load.
+        let failed: HashSet<PathBuf> = self.errors.drain(..).map(|(path, _)| path).collect();
+        self.errors.extend(skipped);
+
+        let mut to_parse = vec![];
+        let mut hashes = HashMap::new();
+        for path in paths {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            match cached.remove(&path) {
+                Some(note) if modified.is_some() && modified <= note.last_edited => {
+                    self.notes.push(note)
+                }
+                stale => {
+                    if let Some(hash) = stale.and_then(|note| note.content_hash) {
+                        hashes.insert(path.clone(), hash);
+                    }
+                    to_parse.push(path)
+                }
+            }
+        }
+
+        let mut changed = vec![];
+        for (path, result) in self._read_many(to_parse) {
+            let same_content = matches!(
+                (&result, hashes.get(&path)),
+                (Ok((note, _)), Some(&hash)) if note.content_hash == Some(hash)
+            );
+            if (self._push_note(path.clone(), result) || !failed.contains(&path)) && !same_content {
+                changed.push(path);
+            }
+        }
+        self.notes.sort_by(|a, b| a.path.cmp(&b.path));
+        // Whatever is left in the cache no longer exists on disk.
+        #[cfg(feature = "search")]
+        for path in cached.keys() {
+            self.terms.remove(path);
+        }
+        changed.extend(cached.into_keys());
+
+        info!("refreshed vault, {} notes changed", changed.len());
+        self._update_indices();
+        self._check_unique_names()?;
+        Ok(changed)
+    }
+
+    pub fn tags(&self) -> HashSet<&str> {
+        self.notes
+            .iter()
+            .flat_map(|note| note.tags.iter().map(String::as_str))
+            .collect()
+    }
+
+    /// Returns all notes carrying the given tag. Tags are matched case-insensitively.
+    pub fn notes_from_tag(&self, tag: &str) -> Vec<&Note> {
+        self.filter(|note| note.has_exact_tag(tag))
+    }
+
+    /// Returns all notes carrying the given tag or any tag nested below it, treating `/` as the
+    /// hierarchy separator. `programming` matches `programming` and `programming/rust/async`, but not
+    /// `programming-languages`.
+    pub fn notes_from_tag_prefix(&self, tag: &str) -> Vec<&Note> {
+        self.filter(|note| note.has_tag(tag))
+    }
+
+    /// Like [`State::tags`], but additionally contains every parent of a hierarchical tag, e.g.
+    /// `programming/rust/async` also adds `programming/rust` and `programming`.
+    pub fn expanded_tags(&self) -> HashSet<&str> {
+        let mut tag_set = self.tags();
+        for tag in self.tags() {
+            let mut parent = tag;
+            while let Some((prefix, _)) = parent.rsplit_once('/') {
+                tag_set.insert(prefix);
+                parent = prefix;
+            }
+        }
         tag_set
     }
 
-    pub fn notes_from_tag(&self, tag: String) -> Vec<Note> {
+    /// Look up a note by its [`Note::id`]. If several notes share an id, the first one in path order
+    /// is returned and the conflict is recorded in `conflicts`.
+    pub fn note_by_id(&self, id: &str) -> Option<&Note> {
+        self.notes.iter().find(|note| note.id == id)
+    }
+
+    /// Look up the note stored at `path`, however the path is written: relative or absolute paths
+    /// and `./` or `..` components all find the same note.
+    pub fn note_by_path(&self, path: &Path) -> Option<&Note> {
+        if let Some(note) = self.notes.iter().find(|note| note.path == path) {
+            return Some(note);
+        }
+        let path = Self::_normalize_path(path);
+        self.notes
+            .iter()
+            .find(|note| Self::_normalize_path(&note.path) == path)
+    }
+
+    /// The canonical form of `path`, or for a file that doesn't exist (anymore) the absolute path
+    /// with `.` and `..` components resolved lexically.
+    fn _normalize_path(path: &Path) -> PathBuf {
+        if let Ok(path) = fs::canonicalize(path) {
+            return path;
+        }
+        let absolute = match path.is_absolute() {
+            true => path.to_path_buf(),
+            false => std::env::current_dir().unwrap_or_default().join(path),
+        };
+        Self::_lexical_normalize(&absolute)
+    }
+
+    /// Look up a note by its name, the file name without extension, or one of its
+    /// [`Note::aliases`]. Names take precedence over aliases. If several notes match, the first one
+    /// in path order is returned, which is also the note `[[name]]` links resolve to. Use
+    /// [`State::notes_by_name`] to get all of them.
+    pub fn note_by_name(&self, name: &str) -> Option<&Note> {
         self.notes
-            .clone()
+            .iter()
+            .find(|note| note.name == name)
+            .or_else(|| self.notes.iter().find(|note| note.aliases.iter().any(|a| a == name)))
+    }
+
+    /// Returns all notes with the given name or alias, in path order.
+    pub fn notes_by_name(&self, name: &str) -> Vec<&Note> {
+        self.filter(|note| note.name == name || note.aliases.iter().any(|a| a == name))
+    }
+
+    /// Every name that's used by more than one note, e.g. `index` for `a/index.md` and
+    /// `b/index.md`, together with all notes using it. Sorted by name, the notes are in path order.
+    pub fn duplicate_names(&self) -> Vec<(String, Vec<&Note>)> {
+        let mut by_name: HashMap<&str, Vec<&Note>> = HashMap::new();
+        for note in &self.notes {
+            by_name.entry(&note.name).or_default().push(note);
+        }
+
+        let mut duplicates = by_name
             .into_iter()
-            .filter(|note| {
-                note.tags
-                    .clone()
-                    .into_iter()
-                    .any(|tag_string| tag_string == tag)
-            })
-            .collect::<Vec<_>>()
+            .filter(|(_, notes)| notes.len() > 1)
+            .map(|(name, notes)| (name.to_string(), notes))
+            .collect::<Vec<_>>();
+        duplicates.sort_by(|a, b| a.0.cmp(&b.0));
+        duplicates
     }
 
-    fn _read_notes(&mut self, path: PathBuf) -> Result<()> {
-        for entry in fs::read_dir(path)? {
-            let entry = entry?;
-            let path = entry.path();
+    /// Returns all notes that are still drafts.
+    pub fn drafts(&self) -> Vec<&Note> {
+        self.filter(|note| note.draft)
+    }
 
-            if path.is_file() && path.extension().unwrap_or_default() == "md" {
-                let name = path.file_stem().unwrap().to_str().unwrap();
-                info!("found note: {:?}", &name);
-                self.notes.push(Note {
-                    name: String::from(name),
-                    tags: Self::_parse_tags(path.clone())?,
-                    path,
-                });
+    /// Returns all notes that were marked as finished.
+    pub fn finished(&self) -> Vec<&Note> {
+        self.filter(|note| !note.draft)
+    }
+
+    /// Returns every note that links to the given note.
+    pub fn backlinks(&self, note: &Note) -> Vec<&Note> {
+        self.backlinks
+            .get(&note.path)
+            .map(|indices| indices.iter().map(|&i| &self.notes[i]).collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether a file at `path` is one of the vault's notes, or would be if it existed: it's inside
+    /// the vault, has a note extension, isn't deeper than [`Config::max_depth`] and neither it nor
+    /// any of its parent directories is skipped. Use this to filter raw file system events down to
+    /// changes of notes.
+    pub fn is_tracked(&self, path: &Path) -> bool {
+        let relative = match path.strip_prefix(&self.path) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+        let mut dir = self.path.clone();
+        let mut components = relative.components().peekable();
+        while let Some(component) = components.next() {
+            dir.push(component);
+            let is_dir = components.peek().is_some();
+            if self._is_skipped(&dir, is_dir) {
+                return false;
             }
         }
+        let depth = relative.components().count().saturating_sub(1);
+        self.config.max_depth.map_or(true, |max| depth <= max) && self.config.is_note(path)
+    }
+
+    /// Every link that doesn't resolve to a note, as the linking note and the target as it's
+    /// written. The same links are reported as [`Error::BrokenLink`] and [`Error::BrokenFileLink`]
+    /// in `link_errors`.
+    pub fn broken_links(&self) -> Vec<(&Note, String)> {
+        let notes: HashMap<&Path, &Note> = self
+            .notes
+            .iter()
+            .map(|note| (note.path.as_path(), note))
+            .collect();
+        self.link_errors
+            .iter()
+            .filter_map(|error| match error {
+                Error::BrokenLink(path, target) | Error::BrokenFileLink(path, target) => {
+                    Some((*notes.get(path.as_path())?, target.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn _read_notes(&mut self) -> Result<()> {
+        let mut paths = vec![];
+        let skipped = self._scan(&mut paths)?;
+        self.errors.extend(skipped);
+
+        for (path, result) in self._read_many(paths) {
+            self._push_note(path, result);
+        }
 
+        info!(
+            "loaded {} notes from {:?}, {} failed",
+            self.notes.len(),
+            &self.path,
+            self.errors.len()
+        );
         Ok(())
     }
 
-    /// Read the notes and parse out relevant information to build internal data structures.
-    fn _parse_tags(note_path: PathBuf) -> Result<Vec<String>> {
-        // Setup the markdown parser.
+    /// Read and parse the notes at the given paths, keeping the order of `paths`.
+    #[cfg(not(feature = "parallel"))]
+    fn _read_many(&self, paths: Vec<PathBuf>) -> Vec<(PathBuf, Result<(Note, String)>)> {
+        paths
+            .into_iter()
+            .map(|path| {
+                let result = self._read_note(&path);
+                (path, result)
+            })
+            .collect()
+    }
+
+    /// Read and parse the notes at the given paths on all available cores. The paths are split into
+    /// one contiguous chunk per thread and the results are joined in order, so the result keeps the
+    /// order of `paths` just like the sequential version.
+    #[cfg(feature = "parallel")]
+    fn _read_many(&self, paths: Vec<PathBuf>) -> Vec<(PathBuf, Result<(Note, String)>)> {
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        let chunk_size = ((paths.len() + threads - 1) / threads).max(1);
+
+        std::thread::scope(|scope| {
+            let handles = paths
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|path| (path.clone(), self._read_note(path)))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("note parsing thread panicked"))
+                .collect()
+        })
+    }
+
+    /// Add a parsed note to the state, or record the error if it couldn't be read. Returns whether
+    /// the note was added.
+    fn _push_note(&mut self, path: PathBuf, result: Result<(Note, String)>) -> bool {
+        match result {
+            Ok((note, _contents)) => {
+                #[cfg(feature = "search")]
+                self.terms
+                    .insert(note.path.clone(), search::term_counts(&_contents));
+                self.notes.push(note);
+                true
+            }
+            Err(e) => {
+                warn!("failed to load note {:?}: {:?}", &path, &e);
+                self.errors.push((path, e));
+                false
+            }
+        }
+    }
+
+    /// Collect the paths of all notes in the vault, honoring its [`IGNORE_FILE`]. Subdirectories
+    /// that can't be read are skipped and returned with the reason why, so they can be added to
+    /// `errors`. Only failing to read the vault directory itself fails the scan.
+    fn _scan(&mut self, paths: &mut Vec<PathBuf>) -> Result<Vec<(PathBuf, Error)>> {
+        self.ignore = IgnorePatterns::load(&self.path)?;
+        let mut skipped = vec![];
+        for path in walk::NotePaths::new(self) {
+            match path {
+                Ok(path) => paths.push(path),
+                Err((path, e)) if path == self.path => return Err(e),
+                Err((path, e)) => {
+                    warn!("failed to read directory {:?}: {:?}", &path, &e);
+                    skipped.push((path, e));
+                }
+            }
+        }
+        Self::_dedup_symlinks(paths);
+        Ok(skipped)
+    }
+
+    /// Keep only one path to each note file when symlinks make a file reachable through several
+    /// paths. The file itself is preferred over a link to it, otherwise the first path in walk
+    /// order is kept.
+    fn _dedup_symlinks(paths: &mut Vec<PathBuf>) {
+        // Canonicalizing every path is only worth it if there are symlinks at all.
+        if !paths.iter().any(|path| path.is_symlink()) {
+            return;
+        }
+
+        let mut by_target: HashMap<PathBuf, usize> = HashMap::new();
+        let mut keep = vec![true; paths.len()];
+        for (i, path) in paths.iter().enumerate() {
+            let target = match fs::canonicalize(path) {
+                Ok(target) => target,
+                Err(_) => continue,
+            };
+            match by_target.get(&target) {
+                Some(&first) if paths[first].is_symlink() && !path.is_symlink() => {
+                    keep[first] = false;
+                    by_target.insert(target, i);
+                }
+                Some(_) => keep[i] = false,
+                None => {
+                    by_target.insert(target, i);
+                }
+            }
+        }
+
+        let mut keep = keep.into_iter();
+        paths.retain(|_| keep.next().unwrap_or(true));
+        debug!("{} notes left after skipping duplicate symlinks", paths.len());
+    }
+
+    /// Whether a file or directory is left out when scanning the vault, because it's hidden, a
+    /// backup, matches the [`IGNORE_FILE`] or is the configured [`Config::templates_dir`]. Doesn't
+    /// look at the parent directories.
+    fn _is_skipped(&self, path: &Path, is_dir: bool) -> bool {
+        let relative = path.strip_prefix(&self.path).unwrap_or(path);
+        let is_templates_dir = || {
+            let templates_dir = self.config.templates_dir.as_deref();
+            templates_dir.is_some_and(|dir| relative == Path::new(dir))
+        };
+        self.config.is_ignored(path)
+            || self.ignore.is_ignored(relative, is_dir)
+            || (is_dir && is_templates_dir())
+    }
+
+    /// Read a single note file and parse out everything the [`Note`] is made of. The raw contents
+    /// are returned alongside for indexing.
+    fn _read_note(&self, path: &Path) -> Result<(Note, String)> {
+        // Read note contents of note files.
+        let mut contents = String::new();
+        fs::File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .map_err(|e| Error::io(path, e))?;
+        let metadata = fs::metadata(path).map_err(|e| Error::io(path, e))?;
+
+        let note = self._parse_note(path.to_path_buf(), &contents)?;
+        let note = Note {
+            created_on: note.created_on.or_else(|| metadata.created().ok()),
+            last_edited: metadata.modified().ok(),
+            ..note
+        };
+        Ok((note, contents))
+    }
+
+    /// Parse the contents of the note at `path`. The file itself isn't accessed, so the times of
+    /// the note are left empty unless its metadata block contains a creation date.
+    fn _parse_note(&self, path: PathBuf, contents: &str) -> Result<Note> {
+        let config = &self.config;
+        let content_hash = Self::_content_hash(contents);
+        let original = Self::_strip_bom(contents);
+        let normalized = Self::_normalize_newlines(original);
+        let contents = normalized.as_ref();
+        // File names that aren't valid UTF-8 are converted lossily instead of being rejected.
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        debug!("found note: {:?}", &name);
+
+        let (frontmatter, display_tags, raw_frontmatter) = match Self::_is_org(&path) {
+            true => {
+                let header = Self::_org_header(contents);
+                let tags = Self::_check_tags(&path, Self::_parse_org_tags(header), config)?;
+                let raw = Some(header.trim_end())
+                    .filter(|header| !header.is_empty())
+                    .map(|header| (FrontmatterKind::Org, header));
+                (Self::_parse_org_keywords(header), tags, raw)
+            }
+            false => {
+                let metadata_text = Self::_metadata_text(contents);
+                let frontmatter = match metadata_text {
+                    Some((MetadataBlockKind::YamlStyle, text, line)) => {
+                        Self::_parse_frontmatter(&path, text, line)?
+                    }
+                    Some((MetadataBlockKind::PlusesStyle, text, line)) => {
+                        Self::_parse_toml_frontmatter(&path, text, line)?
+                    }
+                    None => HashMap::new(),
+                };
+                let metadata_text = metadata_text.map(|(kind, text, _)| (kind, text));
+                let inline = match config.inline_tags {
+                    true => Self::_parse_inline_tags(contents),
+                    false => vec![],
+                };
+                let tags = Self::_parse_tags(&path, metadata_text, &frontmatter, inline, config)?;
+                (frontmatter, tags, Self::_raw_metadata(contents))
+            }
+        };
+
+        let draft = Self::_parse_draft(&path, &frontmatter)?;
+        let created_on = Self::_parse_created(&path, &frontmatter)?;
+        let relative = path.strip_prefix(&self.path).unwrap_or(&path);
+        let display_tags = match config.folder_tags {
+            true => Self::_add_folder_tags(display_tags, relative),
+            false => display_tags,
+        };
+        let id = match frontmatter.get("id") {
+            Some(id) if !id.is_empty() => id.clone(),
+            _ => Self::_slug(relative),
+        };
+
+        let aliases = frontmatter
+            .get("aliases")
+            .map(|value| Self::_parse_aliases(value))
+            .unwrap_or_default();
+
+        let note = Note {
+            id,
+            name,
+            aliases,
+            tags: display_tags.iter().map(|tag| tag.to_lowercase()).collect(),
+            display_tags,
+            frontmatter,
+            raw_frontmatter: raw_frontmatter.map(|(_, raw)| String::from(raw)),
+            frontmatter_kind: raw_frontmatter.map(|(kind, _)| kind),
+            draft,
+            created_on,
+            last_edited: None,
+            content_hash,
+            links: vec![],
+            raw_links: Self::_parse_links(contents),
+            file_links: Self::_parse_file_links(contents)
+                .into_iter()
+                .filter(|target| config.is_note(Path::new(target)))
+                .collect(),
+            external_links: Self::_parse_external_links(contents),
+            cached_content: config.cache_content.then(|| String::from(original)),
+            path,
+        };
+        Ok(note)
+    }
+
+    fn _parser_options() -> pulldown_cmark::Options {
         let mut parser_options = pulldown_cmark::Options::empty();
         parser_options.insert(pulldown_cmark::Options::ENABLE_YAML_STYLE_METADATA_BLOCKS);
         parser_options.insert(pulldown_cmark::Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS);
+        parser_options
+    }
 
-        // Read note contents of note files.
-        let mut contents = String::new();
-        fs::File::open(&note_path)?.read_to_string(&mut contents)?;
+    /// Locate the metadata block of a note and return its kind and the block verbatim,
+    /// including the delimiters.
+    fn _raw_metadata(contents: &str) -> Option<(FrontmatterKind, &str)> {
+        Parser::new_ext(contents, Self::_parser_options())
+            .into_offset_iter()
+            .find_map(|(event, range)| match event {
+                Event::Start(MetadataBlock(MetadataBlockKind::YamlStyle)) => {
+                    Some((FrontmatterKind::Yaml, &contents[range]))
+                }
+                Event::Start(MetadataBlock(MetadataBlockKind::PlusesStyle)) => {
+                    Some((FrontmatterKind::Toml, &contents[range]))
+                }
+                _ => None,
+            })
+    }
+
+    /// Locate the metadata block of a note and return its kind, YAML style `---` or TOML style
+    /// `+++`, its text, excluding the delimiters, and the line of the note the text starts on.
+    fn _metadata_text(contents: &str) -> Option<(MetadataBlockKind, &str, usize)> {
+        // Parse markdown from string. The block is usually at the very top, but blank lines or
+        // other content may come before it.
+        let mut events = Parser::new_ext(contents, Self::_parser_options()).into_offset_iter();
+
+        let kind = events.find_map(|(event, _)| match event {
+            Event::Start(MetadataBlock(kind)) => Some(kind),
+            _ => None,
+        })?;
+
+        // The text is sliced out of the note, however many text events it's split into.
+        let mut text: Option<std::ops::Range<usize>> = None;
+        while let Some((Event::Text(_), range)) = events.next() {
+            text = Some(text.map_or(range.clone(), |text| text.start..range.end));
+        }
+        text.map(|range| {
+            let line = contents[..range.start].matches('\n').count() + 1;
+            (kind, &contents[range], line)
+        })
+    }
+
+    /// Hash the contents of a note for [`Note::content_hash`] with 64 bit FNV-1a, which is fast
+    /// for short texts and doesn't depend on the platform.
+    #[cfg(feature = "hash")]
+    fn _content_hash(contents: &str) -> Option<u64> {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+        let hash = contents
+            .bytes()
+            .fold(OFFSET_BASIS, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(PRIME));
+        Some(hash)
+    }
+
+    #[cfg(not(feature = "hash"))]
+    fn _content_hash(_contents: &str) -> Option<u64> {
+        None
+    }
+
+    /// Turn Windows `\r\n` and old Mac `\r` line endings into `\n`. The parser doesn't recognize
+    /// a metadata block with other line endings.
+    pub(crate) fn _normalize_newlines(contents: &str) -> Cow<'_, str> {
+        match contents.contains('\r') {
+            true => Cow::Owned(contents.replace("\r\n", "\n").replace('\r', "\n")),
+            false => Cow::Borrowed(contents),
+        }
+    }
 
-        // Parse markdown from string.
-        let events = TextMergeStream::new(Parser::new_ext(&contents, parser_options));
+    /// Strip the byte order mark some editors put at the start of a file, which would otherwise
+    /// hide the metadata block.
+    pub(crate) fn _strip_bom(contents: &str) -> &str {
+        contents.strip_prefix('\u{feff}').unwrap_or(contents)
+    }
 
-        // Parse out relevant state information.
-        let meta_data_predicate = |event: &Event| {
-            matches!(
-                event,
-                Event::Start(MetadataBlock(MetadataBlockKind::PlusesStyle))
-                    | Event::Start(MetadataBlock(MetadataBlockKind::YamlStyle))
-            )
+    /// Read the notes and parse out relevant information to build internal data structures.
+    /// Notes without tags are fine unless [`Config::require_tags`] is set. In TOML style metadata
+    /// the tags are an array assigned to the tag key without its colon, e.g. `tags = ["a", "b"]`.
+    /// The `inline` tags of the body are appended to the ones of the metadata block.
+    fn _parse_tags(
+        path: &Path,
+        metadata: Option<(MetadataBlockKind, &str)>,
+        frontmatter: &HashMap<String, String>,
+        inline: Vec<String>,
+        config: &Config,
+    ) -> Result<Vec<String>> {
+        let mut tags = match metadata {
+            Some((MetadataBlockKind::YamlStyle, tag_text)) => {
+                Self::_parse_tag_text(tag_text, &config.tag_key)?
+            }
+            Some((MetadataBlockKind::PlusesStyle, _)) => {
+                let key = config.tag_key.trim_end_matches(':').trim();
+                frontmatter
+                    .get(key)
+                    .map(|value| Self::_split_tag_value(value))
+                    .unwrap_or_default()
+            }
+            None if config.require_tags && inline.is_empty() => {
+                return Err(Error::MetaDataError(
+                    path.to_path_buf(),
+                    String::from("Incorrectly formatted metadata tags or missing entirely."),
+                ))
+            }
+            None => vec![],
         };
+        tags.extend(inline);
+        Self::_check_tags(path, tags, config)
+    }
 
-        let text_event = events.skip_while(meta_data_predicate).next();
+    /// Extract the inline `#tags` of the body of a note, see [`Config::inline_tags`]. A tag is a
+    /// word starting with `#`, followed by the characters [`is_valid_tag`] allows. Headings don't
+    /// match since the parser already removed their `#`, and neither do numbers like `#12` or
+    /// anything inside code and the metadata block.
+    fn _parse_inline_tags(contents: &str) -> Vec<String> {
+        let mut tags = vec![];
+        let mut in_code_or_metadata = false;
 
-        match text_event {
-            Some(Event::Text(CowStr::Borrowed(tag_text))) => {
-                return Ok(Self::_parse_tag_text(tag_text)?);
+        for event in TextMergeStream::new(Parser::new_ext(contents, Self::_parser_options())) {
+            match event {
+                Event::Start(Tag::CodeBlock(_)) | Event::Start(MetadataBlock(_)) => {
+                    in_code_or_metadata = true
+                }
+                Event::End(TagEnd::CodeBlock) | Event::End(TagEnd::MetadataBlock(_)) => {
+                    in_code_or_metadata = false
+                }
+                Event::Text(text) if !in_code_or_metadata => {
+                    for word in text.split_whitespace() {
+                        let tag = match word.strip_prefix('#') {
+                            Some(tag) => tag,
+                            None => continue,
+                        };
+                        let end = tag.find(|c| !tags::is_tag_char(c)).unwrap_or(tag.len());
+                        let tag = tag[..end].trim_end_matches('/');
+                        if is_valid_tag(tag) && !tag.chars().all(|c| c.is_ascii_digit()) {
+                            tags.push(String::from(tag));
+                        }
+                    }
+                }
+                _ => {}
             }
-            _ => {
-                return Err(Error::MetaDataError(format!(
-                    "Incorrectly formatted metadata tags or missing entirely."
-                )))
+        }
+
+        tags
+    }
+
+    /// Reject a note without tags if tags are required, and drop the repeated tags of a note.
+    fn _check_tags(path: &Path, tags: Vec<String>, config: &Config) -> Result<Vec<String>> {
+        if tags.is_empty() && config.require_tags {
+            return Err(Error::MetaDataError(
+                path.to_path_buf(),
+                String::from("Must specify at least one tag."),
+            ));
+        }
+
+        // A tag listed more than once, in any case, only keeps its first occurrence.
+        let mut seen = HashSet::new();
+        Ok(tags
+            .into_iter()
+            .filter(|tag| seen.insert(tag.to_lowercase()))
+            .collect())
+    }
+
+    /// Append the folders of the `relative` path of a note to its tags, skipping the ones it
+    /// already has in any case.
+    fn _add_folder_tags(mut tags: Vec<String>, relative: &Path) -> Vec<String> {
+        let mut seen = tags.iter().map(|tag| tag.to_lowercase()).collect::<HashSet<_>>();
+        let folders = relative
+            .parent()
+            .into_iter()
+            .flat_map(Path::components)
+            .filter_map(|component| match component {
+                Component::Normal(folder) => Some(folder.to_string_lossy().into_owned()),
+                _ => None,
+            });
+        for folder in folders {
+            if seen.insert(folder.to_lowercase()) {
+                tags.push(folder);
             }
         }
+        tags
     }
 
-    fn _parse_tag_text(tag_text: &str) -> Result<Vec<String>> {
-        // Extract only the string of the tag itself
-        let raw_tags: Vec<&str> = tag_text
-            .split('\n')
-            .map(|s| s.trim())
-            .filter(|s| s.starts_with(TAG_IDENTIFIER))
-            .map(|s| s[TAG_IDENTIFIER.len()..].trim())
-            .collect();
+    /// Whether the note at `path` is an Org-mode document instead of markdown.
+    fn _is_org(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("org"))
+    }
 
-        if raw_tags.is_empty() {
-            return Err(Error::MetaDataError(format!(
-                "Must specify at least one tag."
-            )));
+    /// The header of an Org-mode note: the leading lines that are blank, comments or keywords like
+    /// `#+TITLE: Rust`, up to the first line of actual content.
+    fn _org_header(contents: &str) -> &str {
+        let end = contents
+            .split_inclusive('\n')
+            .take_while(|line| line.trim().is_empty() || line.trim_start().starts_with('#'))
+            .map(str::len)
+            .sum();
+        &contents[..end]
+    }
+
+    /// The `#+KEY: value` keywords of an Org-mode header, with their keys lowercased. Only the
+    /// first occurrence of a key is kept.
+    fn _parse_org_keywords(header: &str) -> HashMap<String, String> {
+        let mut keywords = HashMap::new();
+        let lines = header
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("#+")?.split_once(':'));
+        for (key, value) in lines {
+            keywords
+                .entry(key.trim().to_lowercase())
+                .or_insert_with(|| String::from(value.trim()));
         }
+        keywords
+    }
 
-        let tag_collections: Vec<String> = raw_tags
-            .into_iter()
-            .map(|s| {
-                s.split_whitespace()
-                    .map(|s| s.trim_matches(|c| matches!(c, '[' | ']' | ',' | '\"')))
-                    .map(|s| String::from(s))
-                    .collect::<Vec<_>>()
+    /// The tags of an Org-mode header from its `#+TAGS:` and `#+FILETAGS:` lines, written either
+    /// as `a b c` or as `:a:b:c:`. Unlike markdown notes the tag key of the config doesn't apply.
+    fn _parse_org_tags(header: &str) -> Vec<String> {
+        header
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("#+")?.split_once(':'))
+            .filter(|(key, _)| {
+                key.eq_ignore_ascii_case("tags") || key.eq_ignore_ascii_case("filetags")
             })
-            .flatten()
-            .collect();
+            .flat_map(|(_, value)| value.split(|c: char| c == ':' || c.is_whitespace()))
+            .filter(|tag| !tag.is_empty())
+            .map(String::from)
+            .collect()
+    }
 
-        Ok(tag_collections)
+    /// Turn a path into an id like `projects-rust-notes`, without the extension of the file.
+    fn _slug(path: &Path) -> String {
+        let path = path.with_extension("");
+        let mut slug = String::new();
+        for c in path.to_string_lossy().chars() {
+            if c.is_alphanumeric() {
+                slug.extend(c.to_lowercase());
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        String::from(slug.trim_end_matches('-'))
     }
-}
 
-/// The main representation of the application state. This struct contains all necessary
-/// internal information necessary for the application to function.
-#[derive(Debug)]
-pub struct Vault {
-    pub vault_path: PathBuf,
-    pub name: String,
-    pub created_on: Option<SystemTime>,
-    pub state: State,
-}
+    /// Split the value of an `aliases:` field into single aliases. Unlike tags, aliases may contain
+    /// spaces, so only commas and `- alias` list items separate them.
+    fn _parse_aliases(value: &str) -> Vec<String> {
+        let value = value.trim();
+        let value = value
+            .strip_prefix('[')
+            .and_then(|v| v.strip_suffix(']'))
+            .unwrap_or(value);
 
-impl Vault {
-    pub fn new(path: PathBuf) -> Result<Self> {
-        let directory_name = match path.file_name() {
-            Some(x) => String::from(x.to_str().unwrap()),
-            None => return Err(Error::InvalidPath),
-        };
+        value
+            .split([',', '\n'])
+            .map(|alias| {
+                let alias = alias.trim();
+                let alias = alias.strip_prefix('-').unwrap_or(alias).trim();
+                alias.trim_matches(|c| c == '"' || c == '\'')
+            })
+            .filter(|alias| !alias.is_empty())
+            .map(String::from)
+            .collect()
+    }
 
-        info!("Vault-name: {:?}", directory_name);
+    /// A note is a draft unless `draft:` is false or `status:` marks it as finished.
+    fn _parse_draft(path: &Path, frontmatter: &HashMap<String, String>) -> Result<bool> {
+        if let Some(draft) = frontmatter.get("draft") {
+            return match draft.to_lowercase().as_str() {
+                "true" | "yes" => Ok(true),
+                "false" | "no" => Ok(false),
+                _ => Err(Error::MetaDataError(
+                    path.to_path_buf(),
+                    format!("Invalid draft value {:?}, expected true or false", draft),
+                )),
+            };
+        }
 
-        Ok(Self {
-            vault_path: path.clone(),
-            name: directory_name,
-            created_on: None,
-            state: State::new(path)?,
-        })
+        let status = frontmatter.get("status").map(|s| s.to_lowercase());
+        Ok(!matches!(status.as_deref(), Some("finished" | "done")))
     }
-}
 
-pub fn init_tracing() {
-    tracing_subscriber::fmt()
-        .without_time()
-        .with_line_number(true)
-        .with_file(true)
-        .init();
-}
+    /// The creation date of the `created:` field, or else the `date:` field, as an ISO 8601 date
+    /// like `2024-03-01` or `2024-03-01T14:30:00+01:00`.
+    fn _parse_created(
+        path: &Path,
+        frontmatter: &HashMap<String, String>,
+    ) -> Result<Option<SystemTime>> {
+        let value = match frontmatter.get("created").or_else(|| frontmatter.get("date")) {
+            Some(value) => Self::_unquote(value.trim()),
+            None => return Ok(None),
+        };
+        match date::parse_date(value) {
+            Some(created) => Ok(Some(created)),
+            None => Err(Error::MetaDataError(
+                path.to_path_buf(),
+                format!("Invalid date {:?}, expected an ISO 8601 date", value),
+            )),
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Parse every `key: value` pair of the metadata block. Indented lines and `- item` lines are
+    /// continuations of the previous key and get appended to its value on a new line. The block
+    /// starts at line `first_line` of the note, which malformed lines are reported relative to.
+    fn _parse_frontmatter(
+        path: &Path,
+        metadata: &str,
+        first_line: usize,
+    ) -> Result<HashMap<String, String>> {
+        let mut frontmatter: HashMap<String, String> = HashMap::new();
+        let mut last_key: Option<String> = None;
 
-    #[test]
-    fn test_valid() -> Result<()> {
+        for (i, line) in metadata.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
 
-        let valid_path: PathBuf = PathBuf::from("./tests/vault");
-        let vault = Vault::new(valid_path)?;
+            let is_continuation = line.starts_with(char::is_whitespace) || trimmed.starts_with('-');
+            match (is_continuation, &last_key, trimmed.split_once(':')) {
+                (true, Some(key), _) => {
+                    let value = frontmatter.entry(key.clone()).or_default();
+                    if !value.is_empty() {
+                        value.push('\n');
+                    }
+                    value.push_str(trimmed);
+                }
+                (false, _, Some((key, value))) if !key.trim().is_empty() => {
+                    let key = String::from(key.trim());
+                    frontmatter.insert(key.clone(), String::from(value.trim()));
+                    last_key = Some(key);
+                }
+                _ => {
+                    return Err(Error::MalformedMetaData(
+                        path.to_path_buf(),
+                        first_line + i,
+                        format!("Malformed metadata line {:?}", trimmed),
+                    ))
+                }
+            }
+        }
 
-        assert!(vault.name == "vault");
-        assert!(vault.vault_path == PathBuf::from("./tests/vault"));
-        assert!(vault.created_on == None);
+        Ok(frontmatter)
+    }
 
-        let names = vault
-            .state
-            .notes
-            .into_iter()
-            .map(|note| note.name.clone())
-            .collect::<Vec<_>>();
-        let file_names = vec![
-            String::from("TestNote01"),
-            String::from("TestNote02"),
-            String::from("TestNote03"),
-        ];
+    /// Parse every `key = value` pair of a TOML style metadata block. Quoted strings are unquoted,
+    /// arrays are kept as they are written and may span several lines. Keys below a `[table]`
+    /// header are prefixed with the name of the table, e.g. `extra.source`. Like
+    /// [`State::_parse_frontmatter`], malformed lines are reported relative to `first_line`.
+    fn _parse_toml_frontmatter(
+        path: &Path,
+        metadata: &str,
+        first_line: usize,
+    ) -> Result<HashMap<String, String>> {
+        let mut frontmatter: HashMap<String, String> = HashMap::new();
+        let mut table = String::new();
+        // The key of an array that isn't closed yet.
+        let mut open_array: Option<String> = None;
 
-        assert!(file_names == names);
+        for (i, line) in metadata.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
 
-        Ok(())
-    }
+            if let Some(key) = &open_array {
+                let value = frontmatter.entry(key.clone()).or_default();
+                value.push('\n');
+                value.push_str(trimmed);
+                if trimmed.ends_with(']') {
+                    open_array = None;
+                }
+                continue;
+            }
 
-    #[test]
-    fn test_invalid() -> Result<()> {
-        let valid_path: PathBuf =
-            PathBuf::from("./tests/invalid-vault");
-        let vault = Vault::new(valid_path);
+            if let Some(name) = trimmed.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+                table = format!("{}.", name.trim());
+                continue;
+            }
 
-        match vault {
-            Err(Error::MetaDataError(_)) => {
-                return Ok(())
-            },
-            _ => panic!("Test should fail"),
+            match trimmed.split_once('=') {
+                Some((key, value)) if !key.trim().is_empty() => {
+                    let key = format!("{}{}", table, key.trim().trim_matches('"'));
+                    let value = value.trim();
+                    if value.starts_with('[') && !value.ends_with(']') {
+                        open_array = Some(key.clone());
+                    }
+                    frontmatter.insert(key, String::from(Self::_unquote(value)));
+                }
+                _ => {
+                    return Err(Error::MalformedMetaData(
+                        path.to_path_buf(),
+                        first_line + i,
+                        format!("Malformed metadata line {:?}", trimmed),
+                    ))
+                }
+            }
+        }
+
+        Ok(frontmatter)
+    }
+
+    /// Strip a single pair of matching `"` or `'` quotes around a value.
+    fn _unquote(value: &str) -> &str {
+        ['"', '\'']
+            .iter()
+            .find_map(|&q| value.strip_prefix(q).and_then(|v| v.strip_suffix(q)))
+            .unwrap_or(value)
+    }
+
+    /// Extract the targets of all `[[wiki links]]` in the body of a note. Aliases (`[[target|alias]]`)
+    /// and headings (`[[target#heading]]`) are stripped so only the name of the target note remains.
+    /// Links inside code and the metadata block are ignored.
+    fn _parse_links(contents: &str) -> Vec<String> {
+        let mut links = vec![];
+        let mut in_code_or_metadata = false;
+
+        for event in TextMergeStream::new(Parser::new_ext(contents, Self::_parser_options())) {
+            match event {
+                Event::Start(Tag::CodeBlock(_)) | Event::Start(MetadataBlock(_)) => {
+                    in_code_or_metadata = true
+                }
+                Event::End(TagEnd::CodeBlock) | Event::End(TagEnd::MetadataBlock(_)) => {
+                    in_code_or_metadata = false
+                }
+                Event::Text(text) if !in_code_or_metadata => {
+                    let mut rest: &str = &text;
+                    while let Some(start) = rest.find("[[") {
+                        rest = &rest[start + 2..];
+                        let end = match rest.find("]]") {
+                            Some(end) => end,
+                            None => break,
+                        };
+                        let target = rest[..end]
+                            .split(['|', '#'])
+                            .next()
+                            .unwrap_or_default()
+                            .trim();
+                        if !target.is_empty() {
+                            links.push(String::from(target));
+                        }
+                        rest = &rest[end + 2..];
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        links
+    }
+
+    /// Extract the targets of all markdown links that point to a relative path, e.g.
+    /// `[text](Other%20Note.md#heading)` as `Other Note.md`. Links with a scheme like `https:`,
+    /// absolute paths and links to a heading of the note itself are skipped.
+    fn _parse_file_links(contents: &str) -> Vec<String> {
+        Parser::new_ext(contents, Self::_parser_options())
+            .filter_map(|event| match event {
+                Event::Start(Tag::Link { dest_url, .. }) => Self::_file_link_target(&dest_url),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The decoded path a markdown link destination points to, if it's a relative one.
+    fn _file_link_target(dest_url: &str) -> Option<String> {
+        let target = dest_url.split(['#', '?']).next().unwrap_or_default();
+        let has_scheme = target
+            .split_once(':')
+            .is_some_and(|(scheme, _)| !scheme.contains('/'));
+        match has_scheme || target.is_empty() || target.starts_with('/') {
+            true => None,
+            false => Some(Self::_percent_decode(target)),
+        }
+    }
+
+    /// Extract the URLs of all markdown links and autolinks to websites, keeping only the first
+    /// occurrence of each URL.
+    fn _parse_external_links(contents: &str) -> Vec<String> {
+        let mut links: Vec<String> = vec![];
+        for event in Parser::new_ext(contents, Self::_parser_options()) {
+            if let Event::Start(Tag::Link { dest_url, .. }) = event {
+                let is_web = dest_url.split_once("://").is_some_and(|(scheme, _)| {
+                    scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+                });
+                if is_web && !links.iter().any(|link| **link == *dest_url) {
+                    links.push(dest_url.into_string());
+                }
+            }
+        }
+        links
+    }
+
+    /// Decode `%20` and similar escapes of a link target. Invalid escapes are kept as they are.
+    fn _percent_decode(target: &str) -> String {
+        let bytes = target.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let escaped = bytes
+                .get(i + 1..i + 3)
+                .filter(|_| bytes[i] == b'%')
+                .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+            match escaped {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 3;
+                }
+                None => {
+                    decoded.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&decoded).into_owned()
+    }
+
+    /// Escape the characters of a file name that would end or break a markdown link destination.
+    fn _percent_encode(name: &str) -> String {
+        let mut encoded = String::with_capacity(name.len());
+        for c in name.chars() {
+            match c {
+                ' ' => encoded.push_str("%20"),
+                '%' => encoded.push_str("%25"),
+                '(' => encoded.push_str("%28"),
+                ')' => encoded.push_str("%29"),
+                c => encoded.push(c),
+            }
+        }
+        encoded
+    }
+
+    /// Resolve `.` and `..` components of a path without accessing the file system.
+    fn _lexical_normalize(path: &Path) -> PathBuf {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    normalized.pop();
+                }
+                component => normalized.push(component),
+            }
+        }
+        normalized
+    }
+
+    /// Read the notes at `paths` again after they were changed on disk and replace their old
+    /// versions.
+    fn _reload_notes(&mut self, paths: Vec<PathBuf>) {
+        self.notes.retain(|note| !paths.contains(&note.path));
+        self.errors.retain(|(path, _)| !paths.contains(path));
+        for (path, result) in self._read_many(paths) {
+            self._push_note(path, result);
+        }
+        self.notes.sort_by(|a, b| a.path.cmp(&b.path));
+        self._update_indices();
+    }
+
+    /// Read a single new note, add it to the state and return it.
+    fn _add_note(&mut self, path: PathBuf) -> Result<&Note> {
+        let result = self._read_note(&path)?;
+        self._push_note(path.clone(), Ok(result));
+        self.notes.sort_by(|a, b| a.path.cmp(&b.path));
+        self._update_indices();
+
+        Ok(self
+            .notes
+            .iter()
+            .find(|note| note.path == path)
+            .expect("note was just added"))
+    }
+
+    /// Rebuild everything derived from the full set of notes after notes were loaded or refreshed.
+    fn _update_indices(&mut self) {
+        self._resolve_links();
+        self._check_conflicts();
+    }
+
+    /// Record every id, name and alias that's used by more than one note in `conflicts`. An alias
+    /// also conflicts with the name of any other note.
+    fn _check_conflicts(&mut self) {
+        let mut ids: HashSet<&str> = HashSet::new();
+        let mut reported: HashSet<&str> = HashSet::new();
+
+        self.conflicts.clear();
+        for note in &self.notes {
+            if !ids.insert(&note.id) && reported.insert(&note.id) {
+                self.conflicts.push(Error::DuplicateNoteId(note.id.clone()));
+            }
+        }
+        let names = self
+            .duplicate_names()
+            .into_iter()
+            .map(|(name, _)| Error::DuplicateNoteName(name))
+            .collect::<Vec<_>>();
+        self.conflicts.extend(names);
+
+        let names: HashSet<&str> = self.notes.iter().map(|note| note.name.as_str()).collect();
+        let mut aliases: HashSet<&str> = HashSet::new();
+        let mut reported: HashSet<&str> = HashSet::new();
+        for note in &self.notes {
+            for alias in &note.aliases {
+                let taken = (names.contains(alias.as_str()) && *alias != note.name)
+                    || !aliases.insert(alias);
+                if taken && reported.insert(alias) {
+                    self.conflicts.push(Error::DuplicateAlias(alias.clone()));
+                }
+            }
+        }
+    }
+
+    /// Fail with the first duplicate name if [`Config::unique_names`] is set.
+    fn _check_unique_names(&self) -> Result<()> {
+        if !self.config.unique_names {
+            return Ok(());
+        }
+        match self.duplicate_names().into_iter().next() {
+            Some((name, _)) => Err(Error::DuplicateNoteName(name)),
+            None => Ok(()),
+        }
+    }
+
+    /// The name of the note a link target refers to. Links may point into a folder, e.g.
+    /// `[[projects/Note]]`, or include any of the [`Config::extensions`] of the note.
+    fn _link_name<'a>(target: &'a str, config: &Config) -> &'a str {
+        let name = target.rsplit('/').next().unwrap_or(target);
+        match name.rfind('.') {
+            Some(i) if config.is_note(Path::new(name)) => &name[..i],
+            _ => name,
+        }
+    }
+
+    /// Rewrite all `[[links]]` whose target `is_old` so they point to `new_name`, keeping folders,
+    /// extensions, aliases and headings intact. Links in code and the metadata block are left
+    /// alone, just like they are ignored when parsing links. Returns `None` if nothing had to be
+    /// changed.
+    fn _rewrite_links(
+        contents: &str,
+        is_old: impl Fn(&str) -> bool,
+        new_name: &str,
+        config: &Config,
+    ) -> Option<String> {
+        let bom = contents.len() - Self::_strip_bom(contents).len();
+        let protected = Parser::new_ext(Self::_strip_bom(contents), Self::_parser_options())
+            .into_offset_iter()
+            .filter(|(event, _)| {
+                matches!(
+                    event,
+                    Event::Start(Tag::CodeBlock(_)) | Event::Start(MetadataBlock(_)) | Event::Code(_)
+                )
+            })
+            .map(|(_, range)| range.start + bom..range.end + bom)
+            .collect::<Vec<_>>();
+
+        let mut rewritten = String::with_capacity(contents.len());
+        let mut last = 0;
+        let mut offset = 0;
+        let mut changed = false;
+        while let Some(start) = contents[offset..].find("[[").map(|i| offset + i + 2) {
+            let end = match contents[start..].find("]]") {
+                Some(i) => start + i,
+                None => break,
+            };
+            offset = end + 2;
+            if protected.iter().any(|range| range.contains(&start)) {
+                continue;
+            }
+
+            // Only the name itself is replaced, a folder prefix and everything after it stay.
+            let link = &contents[start..end];
+            let target_len = link.find(['|', '#']).unwrap_or(link.len());
+            let target = link[..target_len].trim();
+            if !target.is_empty() && is_old(target) {
+                let target_start = start + link.len() - link.trim_start().len();
+                let name_start = target_start + target.rfind('/').map_or(0, |i| i + 1);
+                rewritten.push_str(&contents[last..name_start]);
+                rewritten.push_str(new_name);
+                last = name_start + Self::_link_name(target, config).len();
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return None;
+        }
+        rewritten.push_str(&contents[last..]);
+        Some(rewritten)
+    }
+
+    /// Rewrite all markdown links like `[text](../Old.md#heading)` whose decoded target `is_old`
+    /// so they point to the file `new_file` in the same folder, keeping the folder and heading
+    /// intact. Returns `None` if nothing had to be changed.
+    fn _rewrite_file_links(
+        contents: &str,
+        is_old: impl Fn(&str) -> bool,
+        new_file: &str,
+    ) -> Option<String> {
+        let bom = contents.len() - Self::_strip_bom(contents).len();
+        let mut rewritten = String::with_capacity(contents.len());
+        let mut last = 0;
+        let mut changed = false;
+        let events = Parser::new_ext(Self::_strip_bom(contents), Self::_parser_options());
+        for (event, range) in events.into_offset_iter() {
+            let dest_url = match event {
+                Event::Start(Tag::Link { dest_url, .. }) => dest_url,
+                _ => continue,
+            };
+            if !Self::_file_link_target(&dest_url).is_some_and(|target| is_old(&target)) {
+                continue;
+            }
+
+            // Find the destination as it's written, reference links have it somewhere else.
+            let link = &contents[range.start + bom..range.end + bom];
+            let dest_start = match link.find("](") {
+                Some(i) => range.start + bom + i + 2,
+                None => continue,
+            };
+            let dest = contents[dest_start..].trim_start();
+            let dest_start = contents.len() - dest.len();
+            let (dest, in_brackets) = match dest.strip_prefix('<') {
+                Some(dest) => (&dest[..dest.find('>').unwrap_or(dest.len())], true),
+                None => (&dest[..dest.find([' ', '\t', '\n', ')']).unwrap_or(dest.len())], false),
+            };
+            let dest_start = dest_start + usize::from(in_brackets);
+
+            // Only the file name is replaced, the folders and a `#heading` stay.
+            let path_len = dest.find(['#', '?']).unwrap_or(dest.len());
+            let name_start = dest_start + dest[..path_len].rfind('/').map_or(0, |i| i + 1);
+            rewritten.push_str(&contents[last..name_start]);
+            match in_brackets {
+                true => rewritten.push_str(new_file),
+                false => rewritten.push_str(&Self::_percent_encode(new_file)),
+            }
+            last = dest_start + path_len;
+            changed = true;
+        }
+
+        if !changed {
+            return None;
+        }
+        rewritten.push_str(&contents[last..]);
+        Some(rewritten)
+    }
+
+    /// Replace the tags declared in the note at `path` by `tags`, leaving the body and all other
+    /// metadata as they are. The tag lines of the metadata block are rewritten in place, or one
+    /// is added. A markdown note without a metadata block gets one.
+    fn _retag(contents: &str, path: &Path, tags: &[String], config: &Config) -> String {
+        // Notes with Windows line endings keep them.
+        if contents.contains("\r\n") {
+            let retagged = Self::_retag(&contents.replace("\r\n", "\n"), path, tags, config);
+            return retagged.replace('\n', "\r\n");
+        }
+        let body = Self::_strip_bom(contents);
+        let bom = &contents[..contents.len() - body.len()];
+        if Self::_is_org(path) {
+            return format!("{}{}", bom, Self::_retag_org(body, tags));
+        }
+
+        let block = Parser::new_ext(body, Self::_parser_options())
+            .into_offset_iter()
+            .find_map(|(event, range)| match event {
+                Event::Start(MetadataBlock(kind)) => Some((kind, range)),
+                _ => None,
+            });
+        let (kind, range) = match block {
+            Some(block) => block,
+            None => {
+                let tag_line = format!("{} {}", config.tag_key, Vault::_format_tags(tags));
+                return format!("{}---\n{}\n---\n\n{}", bom, tag_line, body);
+            }
+        };
+
+        let block = &body[range.clone()];
+        let block = match kind {
+            MetadataBlockKind::YamlStyle => Self::_retag_yaml(block, tags, &config.tag_key),
+            MetadataBlockKind::PlusesStyle => {
+                Self::_retag_toml(block, tags, config.tag_key.trim_end_matches(':').trim())
+            }
+        };
+        format!("{}{}{}{}", bom, &body[..range.start], block, &body[range.end..])
+    }
+
+    /// Rewrite the tag lines of a YAML metadata block, delimiters included. The first tag line
+    /// takes the new tags, further ones and `- tag` list items are dropped.
+    fn _retag_yaml(block: &str, tags: &[String], tag_key: &str) -> String {
+        let tag_line = format!("{} {}\n", tag_key, Vault::_format_tags(tags));
+        let mut lines = vec![];
+        let mut replaced = false;
+        let mut in_tag_list = false;
+        for (i, line) in block.split_inclusive('\n').enumerate() {
+            let trimmed = line.trim();
+            if in_tag_list && trimmed.starts_with('-') && trimmed != "---" {
+                continue;
+            }
+            in_tag_list = i > 0 && trimmed.starts_with(tag_key);
+            match (in_tag_list, replaced) {
+                (true, false) => {
+                    lines.push(tag_line.as_str());
+                    replaced = true;
+                }
+                (true, true) => {}
+                (false, _) => lines.push(line),
+            }
+        }
+        if !replaced {
+            lines.insert(1.min(lines.len()), &tag_line);
+        }
+        lines.concat()
+    }
+
+    /// Rewrite the tag key of a TOML metadata block, delimiters included, as an array of strings.
+    /// Keys inside `[tables]` aren't tags, so a missing tag key is added at the top.
+    fn _retag_toml(block: &str, tags: &[String], key: &str) -> String {
+        let values = tags.iter().map(|tag| format!("{:?}", tag)).collect::<Vec<_>>();
+        let tag_line = format!("{} = [{}]\n", key, values.join(", "));
+        let mut lines = vec![];
+        let mut replaced = false;
+        let mut in_table = false;
+        let mut in_array = false;
+        for (i, line) in block.split_inclusive('\n').enumerate() {
+            let trimmed = line.trim();
+            if in_array {
+                in_array = !trimmed.ends_with(']');
+                continue;
+            }
+            in_table |= i > 0 && trimmed.starts_with('[');
+            let value = match trimmed.split_once('=') {
+                Some((k, value)) if !in_table && k.trim().trim_matches('"') == key => value.trim(),
+                _ => {
+                    lines.push(line);
+                    continue;
+                }
+            };
+            in_array = value.starts_with('[') && !value.ends_with(']');
+            if !replaced {
+                lines.push(tag_line.as_str());
+                replaced = true;
+            }
+        }
+        if !replaced {
+            lines.insert(1.min(lines.len()), &tag_line);
+        }
+        lines.concat()
+    }
+
+    /// Rewrite the `#+TAGS:` and `#+FILETAGS:` lines of an Org-mode note. The first one takes the
+    /// new tags in its own notation, further ones are dropped. If there is none, a `#+FILETAGS:`
+    /// line is added after the other keywords of the header.
+    fn _retag_org(contents: &str, tags: &[String]) -> String {
+        let header = Self::_org_header(contents);
+        let mut lines = vec![];
+        let mut replaced = false;
+        let mut last_keyword = 0;
+        for line in header.split_inclusive('\n') {
+            let keyword = line.trim().strip_prefix("#+").and_then(|l| l.split_once(':'));
+            let keyword = match keyword {
+                Some((keyword, _)) => keyword,
+                None => {
+                    lines.push(String::from(line));
+                    continue;
+                }
+            };
+            let is_filetags = keyword.eq_ignore_ascii_case("filetags");
+            if is_filetags || keyword.eq_ignore_ascii_case("tags") {
+                if !replaced && !tags.is_empty() {
+                    lines.push(Self::_org_tag_line(keyword, tags, is_filetags));
+                }
+                replaced = true;
+            } else {
+                lines.push(String::from(line));
+            }
+            last_keyword = lines.len();
+        }
+        if !replaced && !tags.is_empty() {
+            lines.insert(last_keyword, Self::_org_tag_line("FILETAGS", tags, true));
+        }
+        format!("{}{}", lines.concat(), &contents[header.len()..])
+    }
+
+    fn _org_tag_line(keyword: &str, tags: &[String], is_filetags: bool) -> String {
+        match is_filetags {
+            true => format!("#+{}: :{}:\n", keyword, tags.join(":")),
+            false => format!("#+{}: {}\n", keyword, tags.join(" ")),
+        }
+    }
+
+    /// Resolve the raw link targets of every note to the paths of the notes they point to, by name
+    /// or alias and case-insensitively if there is no exact match. This can
+    /// only happen once all notes are loaded. Unresolvable links are collected in `link_errors` and the
+    /// reverse index used by [`State::backlinks`] is rebuilt.
+    fn _resolve_links(&mut self) {
+        let names = LinkNames::new(&self.notes);
+        let paths_by_file = self
+            .notes
+            .iter()
+            .map(|note| (Self::_lexical_normalize(&note.path), note.path.clone()))
+            .collect::<HashMap<_, _>>();
+
+        self.link_errors.clear();
+        for note in self.notes.iter_mut() {
+            note.links.clear();
+            for target in &note.raw_links {
+                match names.resolve(Self::_link_name(target, &self.config)) {
+                    Some(path) if !note.links.contains(path) => note.links.push(path.clone()),
+                    Some(_) => {}
+                    None => self
+                        .link_errors
+                        .push(Error::BrokenLink(note.path.clone(), target.clone())),
+                }
+            }
+            let folder = note.path.parent().unwrap_or(Path::new(""));
+            for target in &note.file_links {
+                match paths_by_file.get(&Self::_lexical_normalize(&folder.join(target))) {
+                    Some(path) if !note.links.contains(path) => note.links.push(path.clone()),
+                    Some(_) => {}
+                    None => self
+                        .link_errors
+                        .push(Error::BrokenFileLink(note.path.clone(), target.clone())),
+                }
+            }
+        }
+
+        self.backlinks.clear();
+        for (i, note) in self.notes.iter().enumerate() {
+            for link in &note.links {
+                self.backlinks.entry(link.clone()).or_default().push(i);
+            }
+        }
+    }
+
+    /// Collect the tags of every tag line. Besides inline values, YAML block lists following the tag
+    /// key on their own `- tag` lines are supported.
+    fn _parse_tag_text(tag_text: &str, tag_key: &str) -> Result<Vec<String>> {
+        let mut tag_collections: Vec<String> = vec![];
+        let mut lines = tag_text.split('\n').map(|s| s.trim()).peekable();
+
+        while let Some(line) = lines.next() {
+            // Extract only the string of the tag itself
+            let value = match line.strip_prefix(tag_key) {
+                Some(value) => value,
+                None => continue,
+            };
+            tag_collections.extend(Self::_split_tag_value(value));
+
+            while let Some(item) = lines.peek().and_then(|l| l.strip_prefix('-')) {
+                tag_collections.extend(Self::_split_tag_value(item));
+                lines.next();
+            }
+        }
+
+        Ok(tag_collections)
+    }
+
+    /// Split the value of a tag line into single tags. Both plain lists (`a b, c`) and YAML flow
+    /// sequences (`[a, b, c]`) are supported. Quoted tags (`"machine learning"`) are kept together.
+    fn _split_tag_value(value: &str) -> Vec<String> {
+        let value = value.trim();
+        let value = value
+            .strip_prefix('[')
+            .and_then(|v| v.strip_suffix(']'))
+            .unwrap_or(value);
+
+        let mut tags = vec![];
+        let mut current = String::new();
+        let mut quote: Option<char> = None;
+
+        for c in value.chars() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), c) => current.push(c),
+                (None, '"' | '\'') => quote = Some(c),
+                (None, c) if c == ',' || c.is_whitespace() => {
+                    if !current.is_empty() {
+                        tags.push(std::mem::take(&mut current));
+                    }
+                }
+                (None, c) => current.push(c),
+            }
+        }
+        if !current.is_empty() {
+            tags.push(current);
+        }
+
+        tags
+    }
+}
+
+/// The paths of the notes `[[wiki links]]` can refer to, by name or alias.
+struct LinkNames {
+    exact: HashMap<String, PathBuf>,
+    /// Links that don't match exactly fall back to a case-insensitive match.
+    lowercase: HashMap<String, PathBuf>,
+}
+
+impl LinkNames {
+    fn new(notes: &[Note]) -> Self {
+        let mut link_names = Self {
+            exact: HashMap::new(),
+            lowercase: HashMap::new(),
+        };
+        // Aliases are added after all names, so they never shadow the actual name of a note.
+        let names = notes.iter().map(|note| (&note.name, &note.path));
+        let aliases = notes
+            .iter()
+            .flat_map(|note| note.aliases.iter().map(move |alias| (alias, &note.path)));
+        for (name, path) in names.chain(aliases) {
+            link_names
+                .exact
+                .entry(name.clone())
+                .or_insert_with(|| path.clone());
+            link_names
+                .lowercase
+                .entry(name.to_lowercase())
+                .or_insert_with(|| path.clone());
+        }
+        link_names
+    }
+
+    /// The path of the note called `name`, see [`State::_link_name`].
+    fn resolve(&self, name: &str) -> Option<&PathBuf> {
+        self.exact
+            .get(name)
+            .or_else(|| self.lowercase.get(&name.to_lowercase()))
+    }
+}
+
+/// The main representation of the application state. This struct contains all necessary
+/// internal information necessary for the application to function.
+#[derive(Debug)]
+pub struct Vault {
+    pub vault_path: PathBuf,
+    pub name: String,
+    /// The creation time of the vault directory, `None` where the platform doesn't support it.
+    pub created_on: Option<SystemTime>,
+    pub state: State,
+    /// Templates added with [`Vault::register_template`], by name.
+    pub templates: HashMap<String, String>,
+}
+
+impl Vault {
+    /// Open the vault at `path` with the default options, a shorthand for
+    /// `Vault::builder(path).open()`.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        Self::builder(path).open()
+    }
+
+    /// Open the vault at `path` with the options of its [`CONFIG_FILE`] if it has one, or the
+    /// default options otherwise. A [`TAGS_FILE`] sets the allowed tags.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let config = Self::_load_config(path.as_ref())?;
+        Self::with_config(path, config)
+    }
+
+    /// Like [`Vault::open`], but fails with [`Error::EmptyVault`] if the vault doesn't contain any
+    /// notes, e.g. to tell a brand new vault apart from one that's in use.
+    pub fn open_non_empty(path: impl AsRef<Path>) -> Result<Self> {
+        let vault = Self::open(path)?;
+        match vault.state.is_empty() {
+            true => Err(Error::EmptyVault),
+            false => Ok(vault),
+        }
+    }
+
+    /// The options of the vault at `path`, from its [`CONFIG_FILE`] or the default ones. The
+    /// allowed tags are taken from the [`TAGS_FILE`] if the config file doesn't list them.
+    fn _load_config(path: &Path) -> Result<Config> {
+        Self::_check_path(path)?;
+        let config_path = path.join(CONFIG_FILE);
+        let mut config = match config_path.is_file() {
+            true => Config::from_file(&config_path)?,
+            false => Config::default(),
+        };
+
+        let tags_path = path.join(TAGS_FILE);
+        if config.allowed_tags.is_none() && tags_path.is_file() {
+            config.allowed_tags = Some(Config::read_tags_file(&tags_path)?);
+        }
+        Ok(config)
+    }
+
+    pub fn with_config(path: impl AsRef<Path>, config: Config) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        Self::_check_path(&path)?;
+
+        let directory_name = match path.file_name() {
+            Some(x) => x.to_string_lossy().into_owned(),
+            None => {
+                return Err(Error::InvalidPath(
+                    path,
+                    String::from("can't determine the name of the vault"),
+                ))
+            }
+        };
+
+        info!("Vault-name: {:?}", directory_name);
+
+        Ok(Self {
+            name: directory_name,
+            created_on: Self::_created_on(&path),
+            state: State::with_config(&path, config)?,
+            vault_path: path,
+            templates: HashMap::new(),
+        })
+    }
+
+    /// Turn the vault into a read-only snapshot that can be shared between threads, e.g. by the
+    /// request handlers of a server. All queries of [`State`] only need `&self`, so they work
+    /// through the [`Arc`] without cloning any notes. Use [`Arc::try_unwrap`] to get the vault back
+    /// for changes.
+    pub fn into_shared(self) -> Arc<Self> {
+        Arc::new(self)
+    }
+
+    /// The creation time of the vault directory, if the platform and filesystem record one.
+    pub(crate) fn _created_on(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|metadata| metadata.created()).ok()
+    }
+
+    /// Make sure the vault path points to a readable directory, so that an invalid path is reported
+    /// as such instead of as an IO error somewhere while reading the notes.
+    fn _check_path(path: &Path) -> Result<()> {
+        let invalid = |msg: &str| Error::InvalidPath(path.to_path_buf(), String::from(msg));
+        let describe = |e: std::io::Error| match e.kind() {
+            std::io::ErrorKind::NotFound => invalid("not found"),
+            std::io::ErrorKind::PermissionDenied => invalid("permission denied"),
+            _ => Error::io(path, e),
+        };
+
+        if !fs::metadata(path).map_err(describe)?.is_dir() {
+            return Err(invalid("not a directory"));
+        }
+        fs::read_dir(path).map_err(describe)?;
+        Ok(())
+    }
+
+    /// Create a new note called `name` in the root of the vault. The note starts off with a metadata
+    /// block holding the given tags and today's date, and is added to the state right away. Fails if
+    /// the name isn't a valid file name or a file with that name already exists.
+    pub fn create_note(&mut self, name: &str, tags: &[String]) -> Result<&Note> {
+        Self::_validate_note_name(name)?;
+
+        let path = self.vault_path.join(format!("{}.md", name));
+        let contents = format!(
+            "---\n{} {}\ncreated: {}\n---\n\n",
+            self.state.config.tag_key,
+            Self::_format_tags(tags),
+            date::format_date(SystemTime::now())
+        );
+
+        self._write_new_note(path.clone(), &contents)?;
+        info!("created note: {:?}", &path);
+        self.state._add_note(path)
+    }
+
+    /// Write the file of a new note, failing with [`Error::NoteExists`] if there already is one.
+    fn _write_new_note(&self, path: PathBuf, contents: &str) -> Result<()> {
+        // `create_new` refuses to overwrite existing files, even if one is created concurrently.
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::AlreadyExists => Error::NoteExists(path.clone()),
+                _ => Error::io(&path, e),
+            })
+    }
+
+    /// Rename the note at `old` to `new_name` and rewrite every `[[link]]` and markdown file link
+    /// to it in the other notes of the vault. The note is renamed first and moved back if the
+    /// links can't be rewritten. The rewritten notes are written next to the originals and only
+    /// moved into place once all of them were written, and if moving one of them fails the ones
+    /// before it get their original contents back. A failure leaves the vault untouched unless
+    /// undoing one of these steps fails as well.
+    pub fn rename_note(&mut self, old: &Path, new_name: &str) -> Result<()> {
+        Self::_validate_note_name(new_name)?;
+
+        let note = self
+            .state
+            .notes
+            .iter()
+            .find(|note| note.path == old)
+            .ok_or_else(|| Error::NoteNotFound(old.to_path_buf()))?;
+        let extension = old.extension().and_then(|e| e.to_str()).unwrap_or("md");
+        let new_path = old.with_file_name(format!("{}.{}", new_name, extension));
+        if new_path.exists() {
+            return Err(Error::NoteExists(new_path));
+        }
+
+        // Collect the intended edits first. Only links that resolve to the note are rewritten, the
+        // same way they are resolved while loading the vault. Links to one of its aliases stay.
+        let config = &self.state.config;
+        let names = LinkNames::new(&self.state.notes);
+        let is_old_name = |target: &str| {
+            let name = State::_link_name(target, config);
+            names.resolve(name) == Some(&note.path)
+                && name.to_lowercase() == note.name.to_lowercase()
+        };
+        let old_file = State::_lexical_normalize(old);
+        let new_file = new_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut edits = vec![];
+        let linking = self.state.backlinks(note).into_iter().map(|n| n.path.clone());
+        for path in linking.collect::<HashSet<_>>() {
+            let contents = fs::read_to_string(&path).map_err(|e| Error::io(&path, e))?;
+            let folder = path.parent().unwrap_or(Path::new("")).to_path_buf();
+            let is_old_file =
+                |target: &str| State::_lexical_normalize(&folder.join(target)) == old_file;
+            let wiki = State::_rewrite_links(&contents, is_old_name, new_name, config);
+            let markdown = wiki.as_deref().unwrap_or(&contents);
+            let rewritten = State::_rewrite_file_links(markdown, is_old_file, &new_file).or(wiki);
+            if let Some(rewritten) = rewritten {
+                edits.push((path, rewritten));
+            }
+        }
+
+        // A note linking to itself is rewritten under its new name.
+        for (path, _) in edits.iter_mut() {
+            if path == old {
+                path.clone_from(&new_path);
+            }
+        }
+        fs::rename(old, &new_path).map_err(|e| Error::io(old, e))?;
+        if let Err(e) = Self::_write_all(edits) {
+            let _ = fs::rename(&new_path, old);
+            return Err(e);
+        }
+
+        info!("renamed note {:?} to {:?}", old, &new_path);
+        self.state.refresh()?;
+        Ok(())
+    }
+
+    /// Write the new contents of several existing notes. All of them are first written next to
+    /// the originals and only moved into place once every one of them was written, so a failed
+    /// write leaves the notes untouched. If moving one of them fails, the notes moved before it
+    /// get their original contents back.
+    fn _write_all(edits: Vec<(PathBuf, String)>) -> Result<()> {
+        let mut staged: Vec<(PathBuf, PathBuf, Vec<u8>)> = vec![];
+        for (path, contents) in edits {
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("md");
+            let tmp = path.with_extension(format!("{}.slipbox-tmp", extension));
+            let written = fs::read(&path)
+                .map_err(|e| Error::io(&path, e))
+                .and_then(|original| match fs::write(&tmp, contents) {
+                    Ok(()) => Ok(original),
+                    Err(e) => Err(Error::io(&tmp, e)),
+                });
+            match written {
+                Ok(original) => staged.push((tmp, path, original)),
+                Err(e) => {
+                    for (tmp, _, _) in staged {
+                        let _ = fs::remove_file(tmp);
+                    }
+                    let _ = fs::remove_file(&tmp);
+                    return Err(e);
+                }
+            }
+        }
+
+        for (i, (tmp, path, _)) in staged.iter().enumerate() {
+            if let Err(e) = fs::rename(tmp, path) {
+                for (_, path, original) in &staged[..i] {
+                    let _ = fs::write(path, original);
+                }
+                for (tmp, _, _) in &staged[i..] {
+                    let _ = fs::remove_file(tmp);
+                }
+                return Err(Error::io(path, e));
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the tags of the note at `path` by `new_tags` and write it back to disk. Only the tag
+    /// lines of its metadata block change, the body and all other metadata keep their exact
+    /// formatting. Fails without touching the file if the note wouldn't load with the new tags,
+    /// e.g. without any tags while [`Config::require_tags`] is set.
+    pub fn retag_note(&mut self, path: &Path, new_tags: &[String]) -> Result<()> {
+        if !self.state.notes.iter().any(|note| note.path == path) {
+            return Err(Error::NoteNotFound(path.to_path_buf()));
+        }
+        let contents = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let retagged = State::_retag(&contents, path, new_tags, &self.state.config);
+        self.state._parse_note(path.to_path_buf(), &retagged)?;
+        Self::_write_all(vec![(path.to_path_buf(), retagged)])?;
+
+        info!("retagged note {:?}", path);
+        self.state._reload_notes(vec![path.to_path_buf()]);
+        Ok(())
+    }
+
+    /// Rename the tag `old` to `new` in every note carrying it and return the number of changed
+    /// notes. Tags are matched case-insensitively and a note that already has `new` keeps it only
+    /// once. Tags a note only gets from its folder or body, see [`Config::folder_tags`] and
+    /// [`Config::inline_tags`], can't be renamed this way. All edits are prepared before the first
+    /// note is written, like for [`Vault::rename_note`].
+    pub fn rename_tag(&mut self, old: &str, new: &str) -> Result<usize> {
+        let old = old.to_lowercase();
+        // The tags a note declares itself, without the ones of its folders.
+        let declared = State::_empty(
+            self.state.path.clone(),
+            Config {
+                folder_tags: false,
+                inline_tags: false,
+                require_tags: false,
+                ..self.state.config.clone()
+            },
+        );
+
+        let mut edits = vec![];
+        for note in self.state.notes.iter().filter(|note| note.has_exact_tag(&old)) {
+            let contents = fs::read_to_string(&note.path).map_err(|e| Error::io(&note.path, e))?;
+            let tags = declared._parse_note(note.path.clone(), &contents)?.display_tags;
+            if !tags.iter().any(|tag| tag.to_lowercase() == old) {
+                continue;
+            }
+
+            let mut seen = HashSet::new();
+            let tags = tags
+                .into_iter()
+                .map(|tag| match tag.to_lowercase() == old {
+                    true => String::from(new),
+                    false => tag,
+                })
+                .filter(|tag| seen.insert(tag.to_lowercase()))
+                .collect::<Vec<_>>();
+            let retagged = State::_retag(&contents, &note.path, &tags, &self.state.config);
+            if retagged != contents {
+                self.state._parse_note(note.path.clone(), &retagged)?;
+                edits.push((note.path.clone(), retagged));
+            }
+        }
+
+        let changed = edits.iter().map(|(path, _)| path.clone()).collect::<Vec<_>>();
+        Self::_write_all(edits)?;
+        info!("renamed tag {:?} to {:?} in {} notes", old, new, changed.len());
+        self.state._reload_notes(changed.clone());
+        Ok(changed.len())
+    }
+
+    /// Format tags as a YAML flow sequence, quoting tags that wouldn't survive parsing otherwise.
+    fn _format_tags(tags: &[String]) -> String {
+        let tags = tags
+            .iter()
+            .map(|tag| {
+                if tag.contains(|c: char| c.is_whitespace() || matches!(c, ',' | '[' | ']')) {
+                    format!("\"{}\"", tag)
+                } else {
+                    tag.clone()
+                }
+            })
+            .collect::<Vec<_>>();
+        format!("[{}]", tags.join(", "))
+    }
+
+    fn _validate_note_name(name: &str) -> Result<()> {
+        let invalid = name.trim().is_empty()
+            || name.trim() != name
+            || name.starts_with('.')
+            || name.chars().any(|c| {
+                c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|')
+            });
+
+        if invalid {
+            return Err(Error::InvalidNoteName(String::from(name)));
+        }
+        Ok(())
+    }
+}
+
+/// Install a global subscriber printing the log events of the library up to `level`, e.g.
+/// `Level::INFO` or `LevelFilter::OFF`. Does nothing if the application already installed a
+/// subscriber and returns whether this one was installed.
+pub fn init_tracing(level: impl Into<LevelFilter>) -> bool {
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .without_time()
+        .with_line_number(true)
+        .with_file(true)
+        .try_init()
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid() -> Result<()> {
+
+        let vault = Vault::new("./tests/vault")?;
+
+        assert!(vault.name == "vault");
+        assert!(vault.vault_path == Path::new("./tests/vault"));
+        assert!(vault.created_on == fs::metadata("./tests/vault")?.created().ok());
+        assert!(vault.state.notes.iter().all(|note| note.last_edited.is_some()));
+        let nested = vault.state.note_by_name("TestNote04").unwrap();
+        assert!(nested.relative_path(&vault) == Path::new("projects/TestNote04.md"));
+
+        let absolute = fs::canonicalize("tests/vault/projects/TestNote04.md")?;
+        for path in [
+            absolute.as_path(),
+            Path::new("./tests/vault/projects/TestNote04.md"),
+            Path::new("tests/vault/projects/../projects/./TestNote04.md"),
+        ] {
+            assert!(vault.state.note_by_path(path).map(|note| &note.path) == Some(&nested.path));
+        }
+        assert!(vault.state.note_by_path(Path::new("tests/vault/Missing.md")).is_none());
+
+        let names = vault
+            .state
+            .notes
+            .into_iter()
+            .map(|note| note.name.clone())
+            .collect::<Vec<_>>();
+        let file_names = vec![
+            String::from("TestNote01"),
+            String::from("TestNote02"),
+            String::from("TestNote03"),
+            String::from("TestNote04"),
+        ];
+
+        assert!(file_names == names);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_iter() -> Result<()> {
+        let state = State::new(PathBuf::from("./tests/vault"))?;
+        assert!(state.len() == 4 && !state.is_empty());
+        assert!(state.iter().map(|note| &note.path).eq(state.notes.iter().map(|note| &note.path)));
+
+        let path = scratch_vault("iter");
+        let state = State::new(path.clone())?;
+        assert!(state.is_empty() && state.iter().next().is_none());
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_links() -> Result<()> {
+        let vault = Vault::new(PathBuf::from("./tests/vault"))?;
+        let notes = &vault.state.notes;
+
+        assert!(notes[0].raw_links == vec!["TestNote02", "TestNote04"]);
+        assert!(notes[0].links == vec![notes[1].path.clone(), notes[3].path.clone()]);
+        assert!(notes[2].raw_links == vec!["Missing Note"]);
+        assert!(notes[2].links.is_empty());
+
+        let backlinks = vault.state.backlinks(&notes[3]);
+        assert!(backlinks.len() == 1 && backlinks[0].name == "TestNote01");
+        assert!(vault.state.backlinks(&notes[0]).is_empty());
+
+        match vault.state.link_errors.as_slice() {
+            [Error::BrokenLink(path, target)] => {
+                assert!(*path == notes[2].path);
+                assert!(target == "Missing Note");
+                Ok(())
+            }
+            _ => panic!("Expected exactly one broken link"),
+        }
+    }
+
+    #[test]
+    fn test_broken_links() -> Result<()> {
+        let path = scratch_vault("broken-links");
+        fs::write(
+            path.join("A.md"),
+            "[[b]] [[Gone]] [[Alias]] [[c|shown]] [[Nowhere#heading]]",
+        )?;
+        fs::write(path.join("B.md"), "---\naliases: [alias]\n---\n[[A]]")?;
+
+        let state = State::new(path.clone())?;
+        let broken = state
+            .broken_links()
+            .into_iter()
+            .map(|(note, target)| format!("{}: {}", note.name, target))
+            .collect::<Vec<_>>();
+        assert!(broken == ["A: Gone", "A: c", "A: Nowhere"]);
+        assert!(state.notes[0].links == [state.notes[1].path.clone()]);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_sources() -> Result<()> {
+        let state = State::from_sources(&[
+            ("B", "---\ntags: b\n---\nBack to [[A]]."),
+            ("A", "---\ntags: a\n---\nSee [[B]] and [[C]]."),
+            ("Broken", "---\ntags: a\nnot yaml\n---\n"),
+        ]);
+        assert!(state.iter().map(|note| note.name.as_str()).eq(["A", "B"]));
+        assert!(state.notes[0].links == [PathBuf::from("B.md")]);
+        assert!(state.backlinks(&state.notes[0])[0].name == "B");
+        assert!(state.notes[1].content()? == "---\ntags: b\n---\nBack to [[A]].");
+        assert!(state.link_errors.len() == 1 && state.errors.len() == 1);
+
+        let state = State::from_notes(state.notes.clone());
+        assert!(state.len() == 2 && state.link_errors.len() == 1 && state.errors.is_empty());
+
+        Ok(())
+    }
+
+    /// Creates an empty scratch vault in the system's temp directory.
+    pub(crate) fn scratch_vault(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("slipbox-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unreadable_directory() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = scratch_vault("unreadable");
+        fs::create_dir_all(path.join("locked"))?;
+        fs::write(path.join("locked/Hidden.md"), "")?;
+        fs::write(path.join("Note.md"), "")?;
+        fs::set_permissions(path.join("locked"), fs::Permissions::from_mode(0o000))?;
+
+        // Permissions aren't enforced for every user, e.g. root.
+        if fs::read_dir(path.join("locked")).is_err() {
+            let mut state = State::new(path.clone())?;
+            assert!(state.notes.len() == 1 && state.errors.len() == 1);
+            assert!(state.errors[0].0 == path.join("locked"));
+            state.refresh()?;
+            assert!(state.notes.len() == 1 && state.errors.len() == 1);
+        }
+
+        fs::set_permissions(path.join("locked"), fs::Permissions::from_mode(0o755))?;
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks() -> Result<()> {
+        use std::os::unix::fs::symlink;
+
+        let path = scratch_vault("symlinks");
+        let outside = scratch_vault("symlinks-outside");
+        fs::create_dir_all(path.join("sub"))?;
+        fs::write(path.join("B.md"), "---\ntags: b\n---\n")?;
+        fs::write(outside.join("Elsewhere.md"), "---\ntags: outside\n---\n")?;
+        symlink(path.join("B.md"), path.join("A.md"))?;
+        symlink(outside.join("Elsewhere.md"), path.join("sub/C.md"))?;
+        symlink(&path, path.join("sub/loop"))?;
+
+        let state = State::new(path.clone())?;
+        let names = state.iter().map(|n| n.name.as_str()).collect::<Vec<_>>();
+        assert!(names == ["B", "C"] && state.errors.is_empty());
+        let linked = state.note_by_name("C").unwrap();
+        assert!(linked.path == path.join("sub/C.md") && linked.tags == ["outside"]);
+        let modified = fs::metadata(outside.join("Elsewhere.md"))?.modified().ok();
+        assert!(linked.last_edited == modified);
+
+        fs::remove_dir_all(path)?;
+        fs::remove_dir_all(outside)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_refresh() -> Result<()> {
+        let path = scratch_vault("refresh");
+        fs::write(path.join("A.md"), "---\ntags: a\n---\n")?;
+        fs::write(path.join("B.md"), "---\ntags: b\n---\n")?;
+
+        let mut state = State::new(path.clone())?;
+        assert!(state.refresh()?.is_empty());
+
+        fs::remove_file(path.join("A.md"))?;
+        fs::write(path.join("C.md"), "---\ntags: c\n---\n[[B]]")?;
+        let mut changed = state.refresh()?;
+        changed.sort();
+
+        assert!(changed == vec![path.join("A.md"), path.join("C.md")]);
+        assert!(state.notes.iter().map(|n| n.name.as_str()).collect::<Vec<_>>() == ["B", "C"]);
+        assert!(state.backlinks(&state.notes[0]).len() == 1);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn test_content_hash() -> Result<()> {
+        assert!(State::_content_hash("") == Some(0xcbf2_9ce4_8422_2325));
+        assert!(State::_content_hash("a") == Some(0xaf63_dc4c_8601_ec8c));
+
+        let path = scratch_vault("content-hash");
+        fs::write(path.join("A.md"), "---\ntags: a\n---\n")?;
+        fs::write(path.join("B.md"), "---\ntags: b\n---\n")?;
+        let mut state = State::new(path.clone())?;
+        let hash = state.notes[0].content_hash;
+        assert!(hash.is_some() && hash != state.notes[1].content_hash);
+
+        // Make sure the modification time differs even on coarse file systems.
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(path.join("A.md"), "---\ntags: a\n---\n")?;
+        fs::write(path.join("B.md"), "---\ntags: changed\n---\n")?;
+        assert!(state.refresh()? == [path.join("B.md")]);
+        assert!(state.notes[0].content_hash == hash && state.notes[1].tags == ["changed"]);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_tag_key() -> Result<()> {
+        let path = scratch_vault("tag-key");
+        fs::write(path.join("A.md"), "---\nkeywords: rust notes\n---\n")?;
+
+        let config = Config {
+            tag_key: String::from("keywords:"),
+            ..Config::default()
+        };
+        let state = State::with_config(path.clone(), config)?;
+        assert!(state.notes[0].tags == vec!["rust", "notes"]);
+        assert!(State::new(path.clone())?.notes[0].tags.is_empty());
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_frontmatter() -> Result<()> {
+        let path = scratch_vault("frontmatter");
+        fs::write(
+            path.join("A.md"),
+            "---\ntitle: Some Title\nauthor: me\ntags: a\nsources:\n  - one\n  - two\n---\n",
+        )?;
+
+        let state = State::new(path.clone())?;
+        let frontmatter = &state.notes[0].frontmatter;
+        assert!(frontmatter["title"] == "Some Title");
+        assert!(frontmatter["author"] == "me");
+        assert!(frontmatter["sources"] == "- one\n- two");
+        let raw = "---\ntitle: Some Title\nauthor: me\ntags: a\nsources:\n  - one\n  - two\n---";
+        assert!(state.notes[0].raw_frontmatter.as_deref() == Some(raw));
+
+        fs::write(path.join("A.md"), "No metadata at all")?;
+        assert!(State::new(path.clone())?.notes[0].raw_frontmatter.is_none());
+
+        fs::write(path.join("A.md"), "---\ntags: a\nnot yaml\n---\n")?;
+        match State::new(path.clone())?.errors.as_slice() {
+            [(_, Error::MalformedMetaData(note, 3, _))] => assert!(*note == path.join("A.md")),
+            _ => panic!("Malformed frontmatter should fail"),
+        }
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata_detection() -> Result<()> {
+        let state = State::from_sources(&[
+            ("Bom", "\u{feff}---\ntags: a\n---\n[[Blank]]"),
+            ("Blank", "\n\n---\ntags: b\n---\nBody"),
+            ("Late", "Intro first.\n\n---\ntags: c\n---\n"),
+            ("Plain", "No metadata."),
+        ]);
+        assert!(state.errors.is_empty());
+        let note = |name| state.note_by_name(name).unwrap();
+        assert!(note("Bom").tags == ["a"] && note("Bom").links == [note("Blank").path.clone()]);
+        assert!(note("Bom").to_html(WikiLinks::Preserve)? == "<p>[[Blank]]</p>\n");
+        assert!(note("Blank").tags == ["b"]);
+        assert!(note("Late").tags == ["c"]);
+        assert!(note("Plain").tags.is_empty() && note("Plain").raw_frontmatter.is_none());
+
+        let source = "\u{feff}---\nup: [[Bom]]\n---\n[[Bom]]";
+        let renamed = State::_rewrite_links(source, |t| t == "Bom", "New", &Config::default());
+        assert!(renamed.as_deref() == Some("\u{feff}---\nup: [[Bom]]\n---\n[[New]]"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_toml_frontmatter() -> Result<()> {
+        let path = scratch_vault("toml");
+        fs::write(
+            path.join("A.md"),
+            "+++\ntitle = \"Some Title\"\ntags = [\"rust\", \"machine learning\"]\ndraft = false\n\
+             aliases = [\n  \"First\",\n  \"Alpha\",\n]\n[extra]\nsource = 'book'\n+++\nBody",
+        )?;
+        fs::write(path.join("B.md"), "---\ntags: [yaml, style]\n---\nBody")?;
+
+        let state = State::new(path.clone())?;
+        let note = &state.notes[0];
+        assert!(note.tags == ["rust", "machine learning"]);
+        assert!(note.frontmatter["title"] == "Some Title");
+        assert!(note.frontmatter["extra.source"] == "book");
+        assert!(note.aliases == ["First", "Alpha"]);
+        assert!(!note.draft);
+        assert!(note.frontmatter_kind == Some(FrontmatterKind::Toml));
+        assert!(state.notes[1].tags == ["yaml", "style"]);
+        assert!(state.notes[1].frontmatter_kind == Some(FrontmatterKind::Yaml));
+
+        fs::write(path.join("A.md"), "\n+++\ntitle = 'A'\ntags: [a]\n+++\n")?;
+        assert!(matches!(
+            State::new(path.clone())?.errors.as_slice(),
+            [(_, Error::MalformedMetaData(_, 4, _))]
+        ));
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_tag_lists() -> Result<()> {
+        let parse = |text: &str| State::_parse_tag_text(text, DEFAULT_TAG_KEY);
+
+        assert!(parse("tags: a b c")? == vec!["a", "b", "c"]);
+        assert!(parse("tags: a, b,c")? == vec!["a", "b", "c"]);
+        assert!(parse("tags: [foo, \"bar baz\"]")? == vec!["foo", "bar baz"]);
+        assert!(parse("tags: 'machine learning' rust")? == vec!["machine learning", "rust"]);
+        assert!(parse("tags: []")?.is_empty());
+
+        assert!(parse("tags:\n  - rust\n  - notes\nauthor: me")? == vec!["rust", "notes"]);
+        assert!(parse("tags:\n- \"bar baz\"\n- foo")? == vec!["bar baz", "foo"]);
+        assert!(parse("tags: a\n  - b")? == vec!["a", "b"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_drafts() -> Result<()> {
+        let path = scratch_vault("drafts");
+        fs::write(path.join("A.md"), "---\ntags: a\n---\n")?;
+        fs::write(path.join("B.md"), "---\ndraft: false\n---\n")?;
+        fs::write(path.join("C.md"), "---\nstatus: Finished\n---\n")?;
+        fs::write(path.join("D.md"), "---\ndraft: maybe\n---\n")?;
+
+        let state = State::new(path.clone())?;
+        assert!(state.drafts().len() == 1 && state.drafts()[0].name == "A");
+        assert!(state.finished().len() == 2);
+        assert!(state.errors.len() == 1);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter() {
+        let state = State::from_sources(&[
+            ("A", "---\ntags: a b c d\n---\n"),
+            ("B", "---\ntags: a\ndraft: false\n---\n"),
+            ("C", "---\ntags: a b c d\ndraft: false\n---\n"),
+        ]);
+        let names = |notes: Vec<&Note>| notes.iter().map(|n| n.name.clone()).collect::<Vec<_>>();
+        assert!(names(state.filter(|note| note.tags.len() > 3)) == ["A", "C"]);
+        assert!(names(state.filter(|note| note.tags.len() > 3 && !note.draft)) == ["C"]);
+        assert!(state.filter(|_| false).is_empty());
+        assert!(state.filter(|_| true).len() == state.len());
+    }
+
+    #[test]
+    fn test_ids() -> Result<()> {
+        let state = State::new(PathBuf::from("./tests/vault"))?;
+        assert!(state.notes[3].id == "projects-testnote04");
+        assert!(state.note_by_id("testnote01").unwrap().name == "TestNote01");
+
+        let path = scratch_vault("ids");
+        fs::write(path.join("A.md"), "---\nid: 20240101\n---\n")?;
+        fs::write(path.join("B.md"), "---\nid: 20240101\n---\n")?;
+        let state = State::new(path.clone())?;
+        assert!(state.note_by_id("20240101").unwrap().name == "A");
+        match state.conflicts.as_slice() {
+            [Error::DuplicateNoteId(id)] => assert!(id == "20240101"),
+            _ => panic!("Expected exactly one id conflict"),
+        }
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_names() -> Result<()> {
+        let path = scratch_vault("duplicate-names");
+        fs::create_dir_all(path.join("a"))?;
+        fs::create_dir_all(path.join("b"))?;
+        fs::write(path.join("a/index.md"), "")?;
+        fs::write(path.join("b/index.md"), "")?;
+        fs::write(path.join("index2.md"), "")?;
+
+        let state = State::new(path.clone())?;
+        match state.duplicate_names().as_slice() {
+            [(name, notes)] => {
+                assert!(name == "index");
+                assert!(notes.len() == 2 && notes[0].path == path.join("a/index.md"));
+            }
+            _ => panic!("Expected exactly one duplicate name"),
+        }
+        assert!(matches!(state.conflicts.as_slice(), [Error::DuplicateNoteName(_)]));
+        assert!(state.note_by_name("index").unwrap().path == path.join("a/index.md"));
+        assert!(state.notes_by_name("index").len() == 2);
+        assert!(state.note_by_name("index2").is_some() && state.note_by_name("Index").is_none());
+
+        let config = Config {
+            unique_names: true,
+            ..Config::default()
+        };
+        match State::with_config(path.clone(), config) {
+            Err(Error::DuplicateNoteName(name)) => assert!(name == "index"),
+            _ => panic!("Expected a duplicate name error"),
+        }
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_aliases() -> Result<()> {
+        let path = scratch_vault("aliases");
+        fs::write(path.join("A.md"), "---\naliases: [Alpha Note, 'first']\n---\n[[B]]")?;
+        fs::write(
+            path.join("B.md"),
+            "---\naliases:\n  - Beta\n  - A\n---\n[[Alpha Note]] [[Beta]]",
+        )?;
+        fs::write(path.join("C.md"), "---\naliases: first\n---\n[[first]]")?;
+
+        let state = State::new(path.clone())?;
+        assert!(state.notes[0].aliases == ["Alpha Note", "first"]);
+        assert!(state.notes[1].aliases == ["Beta", "A"]);
+        assert!(state.note_by_name("Alpha Note").unwrap().name == "A");
+        assert!(state.note_by_name("A").unwrap().name == "A");
+        assert!(state.notes_by_name("first").len() == 2);
+        assert!(state.notes[1].links == [state.notes[0].path.clone(), state.notes[1].path.clone()]);
+        assert!(state.notes[2].links == [state.notes[0].path.clone()]);
+
+        let mut conflicts = state.conflicts.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        conflicts.sort();
+        assert!(conflicts == ["duplicate note alias: A", "duplicate note alias: first"]);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_init_tracing() {
+        assert!(init_tracing(LevelFilter::OFF));
+        assert!(!init_tracing(tracing::Level::DEBUG));
+    }
+
+    #[test]
+    fn test_open() -> Result<()> {
+        let path = scratch_vault("open");
+        assert!(Vault::open(path.clone())?.state.is_empty());
+        assert!(matches!(Vault::open_non_empty(path.clone()), Err(Error::EmptyVault)));
+        fs::write(path.join("A.md"), "---\nkeywords: rust\n---\n")?;
+        fs::write(path.join("B.markdown"), "---\nkeywords: notes\n---\n")?;
+
+        let vault = Vault::open_non_empty(path.clone())?;
+        assert!(vault.state.len() == 1 && vault.state.notes[0].tags.is_empty());
+
+        fs::write(
+            path.join(CONFIG_FILE),
+            "tag_key = \"keywords\"\nextensions = [\"md\", \"markdown\"]\n",
+        )?;
+        let vault = Vault::open(path.clone())?;
+        assert!(vault.state.tags() == HashSet::from(["rust", "notes"]));
+
+        fs::write(path.join(CONFIG_FILE), "tag_key = keywords\n")?;
+        match Vault::open(path.clone()) {
+            Err(Error::ConfigError(config_path, _)) => {
+                assert!(config_path == path.join(CONFIG_FILE))
+            }
+            _ => panic!("Expected a config error"),
+        }
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_into_shared() -> Result<()> {
+        let vault = Vault::new("./tests/vault")?.into_shared();
+        let handles = (0..4)
+            .map(|_| {
+                let vault = Arc::clone(&vault);
+                std::thread::spawn(move || vault.state.notes_from_tag("cool").len())
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            assert!(handle.join().unwrap() == vault.state.notes_from_tag("cool").len());
+        }
+        assert!(Arc::try_unwrap(vault).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_note() -> Result<()> {
+        let path = scratch_vault("create");
+        fs::write(path.join("Existing.md"), "[[New Note]]")?;
+        let mut vault = Vault::new(path.clone())?;
+
+        let tags = vec![String::from("rust"), String::from("machine learning")];
+        let note = vault.create_note("New Note", &tags)?;
+        assert!(note.path == path.join("New Note.md"));
+        assert!(note.tags == tags);
+        assert!(note.frontmatter.contains_key("created"));
+        assert!(vault.state.link_errors.is_empty());
+
+        assert!(matches!(vault.create_note("New Note", &[]), Err(Error::NoteExists(_))));
+        assert!(matches!(vault.create_note("a/b", &[]), Err(Error::InvalidNoteName(_))));
+        assert!(matches!(vault.create_note("", &[]), Err(Error::InvalidNoteName(_))));
+        assert!(vault.state.notes.len() == 2);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_retag() {
+        let config = Config::default();
+        let retag = |contents: &str, file: &str, tags: &[&str]| {
+            let tags = tags.iter().map(|tag| String::from(*tag)).collect::<Vec<_>>();
+            State::_retag(contents, Path::new(file), &tags, &config)
+        };
+
+        let yaml =
+            "---\ntitle: Rust\ntags:\n  - old\n  - older\nstatus: done\n---\n# Body\n- item\n";
+        assert!(
+            retag(yaml, "A.md", &["rust", "machine learning"])
+                == "---\ntitle: Rust\ntags: [rust, \"machine learning\"]\nstatus: done\n---\n\
+                    # Body\n- item\n"
+        );
+        let yaml = "\u{feff}---\ntags: a\nid: x\ntags: b\n---\nBody";
+        assert!(retag(yaml, "A.md", &["c"]) == "\u{feff}---\ntags: [c]\nid: x\n---\nBody");
+        assert!(retag("---\nid: x\n---\n", "A.md", &["c"]) == "---\ntags: [c]\nid: x\n---\n");
+        assert!(retag("Body", "A.md", &["c"]) == "---\ntags: [c]\n---\n\nBody");
+
+        let toml =
+            "+++\ntags = [\n  \"a\",\n  \"b\",\n]\ntitle = \"T\"\n[extra]\ntags = \"keep\"\n+++\n";
+        assert!(
+            retag(toml, "A.md", &["c", "d"])
+                == "+++\ntags = [\"c\", \"d\"]\ntitle = \"T\"\n[extra]\ntags = \"keep\"\n+++\n"
+        );
+        assert!(retag("+++\n[extra]\n+++\n", "A.md", &[]) == "+++\ntags = []\n[extra]\n+++\n");
+
+        let org = "#+TITLE: Rust\n#+FILETAGS: :a:b:\n#+TAGS: c\n\n* Heading\n#+TAGS: body\n";
+        assert!(
+            retag(org, "A.org", &["x", "y"])
+                == "#+TITLE: Rust\n#+FILETAGS: :x:y:\n\n* Heading\n#+TAGS: body\n"
+        );
+        assert!(
+            retag("#+TITLE: Rust\n\nText", "A.org", &["x"])
+                == "#+TITLE: Rust\n#+FILETAGS: :x:\n\nText"
+        );
+        assert!(retag("#+tags: a b\nText", "A.org", &["x", "y"]) == "#+tags: x y\nText");
+    }
+
+    #[test]
+    fn test_crlf() -> Result<()> {
+        let path = scratch_vault("crlf");
+        let contents = "---\r\ntitle: Rust\r\ntags: rust\r\n  - notes\r\n---\r\nSee [[Other]].\r\n";
+        fs::write(path.join("Rust.md"), contents)?;
+        fs::write(path.join("Other.md"), "+++\r\ntags = [\"a\", \"b\"]\r\n+++\r\n")?;
+        fs::write(path.join("Mac.md"), "---\rtags: old mac\r---\r")?;
+
+        let config = Config {
+            cache_content: true,
+            ..Config::default()
+        };
+        let mut vault = Vault::with_config(&path, config)?;
+        let rust = vault.state.note_by_name("Rust").unwrap();
+        assert!(rust.tags == ["rust", "notes"] && rust.frontmatter["title"] == "Rust");
+        assert!(rust.links.len() == 1 && rust.cached_content.as_deref() == Some(contents));
+        assert!(vault.state.note_by_name("Other").unwrap().tags == ["a", "b"]);
+        assert!(vault.state.note_by_name("Mac").unwrap().tags == ["old", "mac"]);
+        assert!(!rust.to_html(WikiLinks::Preserve)?.contains("title") && rust.word_count()? == 2);
+
+        vault.retag_note(&path.join("Rust.md"), &[String::from("lang")])?;
+        assert!(
+            fs::read_to_string(path.join("Rust.md"))?
+                == "---\r\ntitle: Rust\r\ntags: [lang]\r\n---\r\nSee [[Other]].\r\n"
+        );
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_retag_note() -> Result<()> {
+        let path = scratch_vault("retag");
+        let contents = "---\ntitle: Rust\ntags: old\n---\n\nSee [[Other]].\n";
+        fs::write(path.join("Rust.md"), contents)?;
+        fs::write(path.join("Other.md"), "")?;
+        let mut vault = Vault::new(path.clone())?;
+
+        let tags = vec![String::from("Rust"), String::from("lang")];
+        vault.retag_note(&path.join("Rust.md"), &tags)?;
+        assert!(
+            fs::read_to_string(path.join("Rust.md"))?
+                == "---\ntitle: Rust\ntags: [Rust, lang]\n---\n\nSee [[Other]].\n"
+        );
+        let note = vault.state.note_by_name("Rust").unwrap();
+        assert!(note.tags == ["rust", "lang"] && note.display_tags == tags);
+        assert!(note.frontmatter["title"] == "Rust" && note.links.len() == 1);
+        assert!(vault.state.len() == 2 && vault.state.notes_from_tag("old").is_empty());
+
+        assert!(matches!(
+            vault.retag_note(&path.join("Missing.md"), &tags),
+            Err(Error::NoteNotFound(_))
+        ));
+        vault.state.config.require_tags = true;
+        assert!(vault.retag_note(&path.join("Rust.md"), &[]).is_err());
+        assert!(vault.state.note_by_name("Rust").unwrap().tags == ["rust", "lang"]);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_tag() -> Result<()> {
+        let path = scratch_vault("rename-tag");
+        fs::create_dir_all(path.join("rsut"))?;
+        fs::write(path.join("A.md"), "---\ntags: Rsut notes\n---\nBody")?;
+        fs::write(path.join("B.md"), "---\ntags: rust rsut\n---\n")?;
+        fs::write(path.join("C.md"), "---\ntags: python\n---\n")?;
+        fs::write(path.join("rsut/D.md"), "---\ntags: notes\n---\n")?;
+        let mut vault = Vault::builder(path.clone()).folder_tags(true).open()?;
+        assert!(vault.state.notes_from_tag("rsut").len() == 3);
+
+        assert!(vault.rename_tag("RSUT", "rust")? == 2);
+        assert!(fs::read_to_string(path.join("A.md"))? == "---\ntags: [rust, notes]\n---\nBody");
+        assert!(fs::read_to_string(path.join("B.md"))? == "---\ntags: [rust]\n---\n");
+        assert!(fs::read_to_string(path.join("rsut/D.md"))? == "---\ntags: notes\n---\n");
+        assert!(vault.state.notes_from_tag("rust").len() == 2);
+        assert!(vault.state.notes_from_tag("rsut").len() == 1);
+        assert!(vault.rename_tag("missing", "rust")? == 0);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_note() -> Result<()> {
+        let path = scratch_vault("rename");
+        fs::write(path.join("Old.md"), "Self link [[Old#top]]")?;
+        fs::write(
+            path.join("Other.md"),
+            "See [[Old]], [[ dir/Old | alias ]] and [[Oldest]].\n\n```\n[[Old]]\n```\n",
+        )?;
+        let mut vault = Vault::new(path.clone())?;
+
+        vault.rename_note(&path.join("Old.md"), "New")?;
+        assert!(!path.join("Old.md").exists());
+        assert!(fs::read_to_string(path.join("New.md"))? == "Self link [[New#top]]");
+        assert!(
+            fs::read_to_string(path.join("Other.md"))?
+                == "See [[New]], [[ dir/New | alias ]] and [[Oldest]].\n\n```\n[[Old]]\n```\n"
+        );
+
+        let names = vault.state.notes.iter().map(|n| n.name.as_str()).collect::<Vec<_>>();
+        assert!(names == ["New", "Other"]);
+        assert!(vault.state.backlinks(&vault.state.notes[0]).len() == 2);
+        assert!(matches!(
+            vault.rename_note(&path.join("Old.md"), "Newer"),
+            Err(Error::NoteNotFound(_))
+        ));
+
+        // A link that can't be rewritten moves the note back.
+        fs::create_dir(path.join("Other.md.slipbox-tmp"))?;
+        let other = fs::read_to_string(path.join("Other.md"))?;
+        assert!(vault.rename_note(&path.join("New.md"), "Newer").is_err());
+        assert!(path.join("New.md").exists() && !path.join("Newer.md").exists());
+        assert!(fs::read_to_string(path.join("New.md"))? == "Self link [[New#top]]");
+        assert!(fs::read_to_string(path.join("Other.md"))? == other);
+        assert!(!path.join("New.md.slipbox-tmp").exists());
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_note_case_insensitive() -> Result<()> {
+        let path = scratch_vault("rename-case");
+        fs::write(path.join("Old Note.md"), "---\naliases: [Previous]\n---\n")?;
+        fs::write(
+            path.join("Other.md"),
+            "[[old note]], [[OLD NOTE.MD#intro|x]], [[Previous]] and `[[old note]]`",
+        )?;
+        let mut vault = Vault::new(path.clone())?;
+
+        vault.rename_note(&path.join("Old Note.md"), "New Name")?;
+        assert!(
+            fs::read_to_string(path.join("Other.md"))?
+                == "[[New Name]], [[New Name.MD#intro|x]], [[Previous]] and `[[old note]]`"
+        );
+        assert!(vault.state.link_errors.is_empty());
+        assert!(vault.state.backlinks(&vault.state.notes[0]).len() == 1);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_note_file_links() -> Result<()> {
+        let path = scratch_vault("rename-file-links");
+        fs::create_dir_all(path.join("sub"))?;
+        fs::write(path.join("Old Note.md"), "[self](Old%20Note.md)")?;
+        fs::write(path.join("Else.md"), "")?;
+        fs::write(
+            path.join("sub/Other.md"),
+            "[a](../Old%20Note.md#top), [b](<../Old Note.md> \"title\"), [c](../Else.md)",
+        )?;
+        let mut vault = Vault::new(path.clone())?;
+
+        vault.rename_note(&path.join("Old Note.md"), "New (1)")?;
+        assert!(fs::read_to_string(path.join("New (1).md"))? == "[self](New%20%281%29.md)");
+        assert!(
+            fs::read_to_string(path.join("sub/Other.md"))?
+                == "[a](../New%20%281%29.md#top), [b](<../New (1).md> \"title\"), [c](../Else.md)"
+        );
+        assert!(vault.state.link_errors.is_empty());
+        let renamed = vault.state.note_by_name("New (1)").unwrap();
+        assert!(vault.state.backlinks(renamed).len() == 2);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_extensions() -> Result<()> {
+        let path = scratch_vault("extensions");
+        fs::write(path.join("A.md"), "[[C.markdown]] [[B.Md]] [[D.txt]]")?;
+        fs::write(path.join("B.MD"), "")?;
+        fs::write(path.join("C.markdown"), "")?;
+        fs::write(path.join("D.txt"), "")?;
+        fs::write(path.join("E.org"), "")?;
+
+        assert!(State::new(path.clone())?.notes.len() == 3);
+
+        let config = Config {
+            extensions: vec![String::from("md"), String::from("markdown")],
+            ..Config::default()
+        };
+        let state = State::with_config(path.clone(), config)?;
+        assert!(state.notes.len() == 3);
+        assert!(state.notes[0].links == [path.join("C.markdown"), path.join("B.MD")]);
+        assert!(state.broken_links().into_iter().map(|(_, t)| t).collect::<Vec<_>>() == ["D.txt"]);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_links() -> Result<()> {
+        let path = scratch_vault("file-links");
+        fs::create_dir_all(path.join("projects"))?;
+        fs::write(
+            path.join("Index.md"),
+            "[Rust](projects/Rust%20Notes.md#ownership) and [again](./projects/Rust%20Notes.md), \
+             [[Inbox]], [site](https://example.com/a.md), [me](#top), [pdf](paper.pdf), \
+             [gone](Missing.md)\n\n`[code](Inbox.md)`",
+        )?;
+        fs::write(path.join("projects/Rust Notes.md"), "Back to [the index](../Index.md).")?;
+        fs::write(path.join("Inbox.md"), "")?;
+
+        let state = State::new(path.clone())?;
+        let index = state.note_by_name("Index").unwrap();
+        assert!(index.raw_links == ["Inbox"]);
+        assert!(
+            index.file_links
+                == ["projects/Rust Notes.md", "./projects/Rust Notes.md", "Missing.md"]
+        );
+        let rust = state.note_by_name("Rust Notes").unwrap();
+        assert!(index.links == [path.join("Inbox.md"), rust.path.clone()]);
+        assert!(rust.links == [index.path.clone()]);
+        assert!(state.backlinks(rust).len() == 1);
+        let broken = state.broken_links();
+        assert!(broken.len() == 1 && broken[0].1 == "Missing.md");
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_external_links() -> Result<()> {
+        let state = State::from_sources(&[(
+            "A",
+            "See [docs](https://doc.rust-lang.org/book/) and <HTTP://example.com>, \
+             [again](https://doc.rust-lang.org/book/), [mail](mailto:me@example.com), \
+             [note](B.md) and [ftp](ftp://example.com).\n\n    [code](https://skipped.org)\n",
+        )]);
+        let links = &state.notes[0].external_links;
+        assert!(links == &["https://doc.rust-lang.org/book/", "HTTP://example.com"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frontmatter_dates() -> Result<()> {
+        let path = scratch_vault("dates");
+        fs::write(path.join("A.md"), "---\ncreated: 2024-03-01\ndate: 2020-01-01\n---\n")?;
+        fs::write(path.join("B.md"), "---\ndate: \"2024-03-01T12:00:00+02:00\"\n---\n")?;
+        fs::write(path.join("C.md"), "---\ntags: a\n---\n")?;
+        fs::write(path.join("D.md"), "---\ncreated: last tuesday\n---\n")?;
+
+        let state = State::new(path.clone())?;
+        let created = |name| state.note_by_name(name).unwrap().created_on;
+        let march = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_709_251_200);
+        assert!(created("A") == Some(march));
+        assert!(created("B") == Some(march + std::time::Duration::from_secs(10 * 3600)));
+        assert!(created("C") == fs::metadata(path.join("C.md"))?.created().ok());
+        assert!(state.len() == 3 && state.errors.len() == 1);
+        assert!(matches!(&state.errors[0], (p, Error::MetaDataError(_, _)) if p.ends_with("D.md")));
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_folder_tags() -> Result<()> {
+        let path = scratch_vault("folder-tags");
+        fs::create_dir_all(path.join("projects/rust"))?;
+        fs::write(path.join("projects/rust/Ownership.md"), "---\ntags: Rust lang\n---\n")?;
+        fs::write(path.join("Inbox.md"), "---\ntags: todo\n---\n")?;
+
+        let state = State::new(path.clone())?;
+        assert!(state.note_by_name("Ownership").unwrap().tags == ["rust", "lang"]);
+
+        let config = Config {
+            folder_tags: true,
+            ..Config::default()
+        };
+        let state = State::with_config(path.clone(), config)?;
+        let ownership = state.note_by_name("Ownership").unwrap();
+        assert!(ownership.display_tags == ["Rust", "lang", "projects"]);
+        assert!(ownership.tags == ["rust", "lang", "projects"]);
+        assert!(state.note_by_name("Inbox").unwrap().tags == ["todo"]);
+        assert!(state.notes_from_tag("projects").len() == 1);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_inline_tags() -> Result<()> {
+        let contents = "---\ntags: rust\n---\n# Heading\n\nOn #async and #Rust, not a#b or #12.\n\n\
+                        #tokio/runtime/ `#code`\n\n```\n#fenced\n```\n";
+        let state = State::from_sources(&[("A", contents), ("B", "Only #inline here.")]);
+        assert!(state.notes[0].tags == ["rust"] && state.notes[1].tags.is_empty());
+
+        let path = scratch_vault("inline-tags");
+        fs::write(path.join("A.md"), contents)?;
+        fs::write(path.join("B.md"), "Only #inline here.")?;
+        let config = Config {
+            inline_tags: true,
+            require_tags: true,
+            ..Config::default()
+        };
+        let state = State::with_config(path.clone(), config)?;
+        assert!(state.errors.is_empty());
+        let a = state.note_by_name("A").unwrap();
+        assert!(a.display_tags == ["rust", "async", "tokio/runtime"]);
+        assert!(state.note_by_name("B").unwrap().tags == ["inline"]);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_org_notes() -> Result<()> {
+        let path = scratch_vault("org");
+        fs::write(
+            path.join("Rust.org"),
+            "#+TITLE: Rust\n#+TAGS: Rust lang\n#+filetags: :notes:rust:\n#+ID: rs\n\n\
+             * Ownership\nSee [[Python]].\n#+TAGS: ignored\n",
+        )?;
+        fs::write(path.join("Python.md"), "---\ntags: lang\n---\n")?;
+        fs::write(path.join("Empty.org"), "* Nothing here\n")?;
+
+        let state = State::new(path.clone())?;
+        let rust = state.note_by_name("Rust").unwrap();
+        assert!(rust.display_tags == ["Rust", "lang", "notes"]);
+        assert!(rust.id == "rs" && rust.frontmatter["title"] == "Rust");
+        assert!(rust.raw_frontmatter.as_deref().unwrap().ends_with("#+ID: rs"));
+        assert!(rust.frontmatter_kind == Some(FrontmatterKind::Org));
+        assert!(rust.links.len() == 1);
+        assert!(state.notes_from_tag("lang").len() == 2);
+        let empty = state.note_by_name("Empty").unwrap();
+        assert!(empty.tags.is_empty() && empty.raw_frontmatter.is_none());
+        assert!(empty.frontmatter_kind.is_none());
+
+        let config = Config {
+            require_tags: true,
+            ..Config::default()
+        };
+        let state = State::with_config(path.clone(), config)?;
+        assert!(state.has_errors() && state.len() == 2);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hidden_files() -> Result<()> {
+        let path = scratch_vault("hidden");
+        fs::create_dir_all(path.join(".trash"))?;
+        fs::write(path.join(".trash/Deleted.md"), "")?;
+        fs::write(path.join(".Hidden.md"), "")?;
+        fs::write(path.join("Note.md"), "")?;
+        fs::write(path.join("Note.md~"), "")?;
+        fs::write(path.join("Note.md.bak"), "")?;
+
+        let config = Config {
+            extensions: vec![String::from("md"), String::from("md~"), String::from("bak")],
+            ..Config::default()
+        };
+        let state = State::with_config(path.clone(), config.clone())?;
+        assert!(state.iter().map(|note| note.name.as_str()).eq(["Note"]));
+
+        let config = Config {
+            skip_hidden: false,
+            backup_suffixes: vec![],
+            ..config
+        };
+        assert!(State::with_config(path.clone(), config)?.len() == 5);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_file() -> Result<()> {
+        let path = scratch_vault("ignore");
+        fs::create_dir_all(path.join("drafts"))?;
+        fs::create_dir_all(path.join("archive/2023"))?;
+        fs::write(path.join("drafts/Daily.md"), "")?;
+        fs::write(path.join("archive/2023/Old.md"), "")?;
+        fs::write(path.join("archive/Keep.md"), "")?;
+        fs::write(path.join("Note.md"), "")?;
+
+        let mut state = State::new(path.clone())?;
+        assert!(state.len() == 4);
+
+        fs::write(path.join(IGNORE_FILE), "drafts/\narchive/**/*.md\n")?;
+        state.refresh()?;
+        assert!(state.iter().map(|note| note.name.as_str()).eq(["Note"]));
+
+        assert!(state.is_tracked(&path.join("Note.md")) && state.is_tracked(&path.join("New.md")));
+        assert!(!state.is_tracked(&path.join("drafts/Weekly.md")));
+        assert!(!state.is_tracked(&path.join("archive/2024/New.md")));
+        assert!(!state.is_tracked(&path.join(".trash/Note.md")));
+        assert!(!state.is_tracked(&path.join("image.png")));
+        assert!(!state.is_tracked(Path::new("/elsewhere/Note.md")));
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_name() -> Result<()> {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = scratch_vault("non-utf8");
+        fs::write(path.join(OsStr::from_bytes(b"Caf\xe9.md")), "---\ntags: a\n---\n")?;
+
+        let state = State::new(path.clone())?;
+        assert!(state.notes.len() == 1 && state.notes[0].name == "Caf\u{FFFD}");
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_path() {
+        let message = |path: &str| match Vault::new(PathBuf::from(path)) {
+            Err(Error::InvalidPath(_, msg)) => msg,
+            _ => panic!("{} should be an invalid vault path", path),
+        };
+
+        assert!(message("./tests/does-not-exist") == "not found");
+        assert!(message("./tests/vault/TestNote01.md") == "not a directory");
+    }
+
+    #[test]
+    fn test_content() -> Result<()> {
+        let path = scratch_vault("content");
+        fs::write(path.join("A.md"), "Original")?;
+
+        let config = Config {
+            cache_content: true,
+            ..Config::default()
+        };
+        let cached = State::with_config(path.clone(), config)?;
+        let uncached = State::new(path.clone())?;
+        assert!(uncached.notes[0].cached_content.is_none());
+
+        fs::write(path.join("A.md"), "Changed")?;
+        assert!(cached.notes[0].content()? == "Original");
+        assert!(uncached.notes[0].content()? == "Changed");
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_tag_case() -> Result<()> {
+        let path = scratch_vault("tag-case");
+        fs::write(path.join("A.md"), "---\ntags: Rust\n---\n")?;
+        fs::write(path.join("B.md"), "---\ntags: rust\n---\n")?;
+
+        let state = State::new(path.clone())?;
+        assert!(state.tags() == HashSet::from(["rust"]));
+        assert!(state.notes_from_tag("RUST").len() == 2);
+        assert!(state.notes[0].display_tags == vec!["Rust"]);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeated_tags() -> Result<()> {
+        let state = State::from_sources(&[(
+            "A",
+            "---\ntags: rust notes\nkeywords: x\ntags: [Rust, go, notes]\n---\n",
+        )]);
+        assert!(state.notes[0].display_tags == ["rust", "notes", "go"]);
+        assert!(state.notes[0].tags == ["rust", "notes", "go"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nested_tags() -> Result<()> {
+        let path = scratch_vault("nested-tags");
+        fs::write(path.join("A.md"), "---\ntags: programming/rust/async\n---\n")?;
+        fs::write(path.join("B.md"), "---\ntags: programming\n---\n")?;
+        fs::write(path.join("C.md"), "---\ntags: programming-languages\n---\n")?;
+
+        let state = State::new(path.clone())?;
+        let names = |notes: Vec<&Note>| notes.iter().map(|n| n.name.clone()).collect::<Vec<_>>();
+        assert!(names(state.notes_from_tag_prefix("programming")) == ["A", "B"]);
+        assert!(names(state.notes_from_tag_prefix("programming/rust")) == ["A"]);
+        assert!(state.expanded_tags().contains("programming/rust"));
+        assert!(state.expanded_tags().len() == 4);
+
+        let note = state.note_by_name("A").unwrap();
+        assert!(note.has_tag("Programming") && note.has_tag("programming/RUST"));
+        assert!(!note.has_tag("programming/ru") && !note.has_exact_tag("programming"));
+        assert!(note.has_exact_tag("Programming/Rust/Async"));
+        assert!(!state.note_by_name("C").unwrap().has_tag("programming"));
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json() -> Result<()> {
+        let vault = Vault::new(PathBuf::from("./tests/vault"))?;
+        let json: serde_json::Value = serde_json::from_str(&vault.to_json()?)?;
+
+        let note = &json["state"]["notes"][3];
+        assert!(json["name"] == "vault");
+        assert!(note["path"] == "projects/TestNote04.md");
+        assert!(note["tags"] == serde_json::json!(["project", "rust"]));
+        assert!(note["last_edited"].is_u64());
+        assert!(note["frontmatter_kind"] == "Yaml");
+        assert!(json["state"]["notes"][0]["links"][1] == "projects/TestNote04.md");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid() -> Result<()> {
+        let valid_path: PathBuf =
+            PathBuf::from("./tests/invalid-vault");
+
+        // Missing tags are only an error when tagging is enforced.
+        let vault = Vault::new(valid_path.clone())?;
+        assert!(!vault.state.has_errors());
+        assert!(vault.state.notes.iter().all(|note| note.tags.is_empty()));
+
+        let config = Config {
+            require_tags: true,
+            ..Config::default()
+        };
+        let vault = Vault::with_config(valid_path, config)?;
+
+        assert!(vault.state.notes.is_empty());
+        assert!(vault.state.has_errors());
+
+        let failed = vault
+            .state
+            .errors
+            .iter()
+            .map(|(path, e)| (path.file_name().unwrap().to_str().unwrap(), e))
+            .collect::<Vec<_>>();
+
+        match failed.as_slice() {
+            [
+                ("CorruptTags.md", Error::MetaDataError(_, _)),
+                ("MissingTags.md", Error::MetaDataError(_, _)),
+            ] => Ok(()),
+            _ => panic!("Both notes should fail to load"),
         }
     }
 }
@@ -0,0 +1,124 @@
+//! Loading a [`Vault`] without blocking an async runtime, enabled by the `async` feature.
+//!
+//! Reading and parsing the notes happens on a separate thread, the returned future only waits
+//! for it to finish. This works with any executor, e.g. tokio, without depending on one.
+
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use crate::{Config, Result, Vault};
+
+struct Shared<T> {
+    result: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// Future resolving to a [`Vault`] once it is loaded, see [`Vault::new_async`].
+pub struct LoadVault {
+    shared: Arc<Mutex<Shared<thread::Result<Result<Vault>>>>>,
+}
+
+impl LoadVault {
+    fn spawn<F>(load: F) -> Self
+    where
+        F: FnOnce() -> Result<Vault> + Send + 'static,
+    {
+        let shared = Arc::new(Mutex::new(Shared {
+            result: None,
+            waker: None,
+        }));
+        let sender = Arc::clone(&shared);
+        thread::spawn(move || {
+            // A panic while loading is passed on to whoever polls the future.
+            let result = panic::catch_unwind(AssertUnwindSafe(load));
+            let mut shared = sender.lock().unwrap_or_else(|e| e.into_inner());
+            shared.result = Some(result);
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        });
+        Self { shared }
+    }
+}
+
+impl Future for LoadVault {
+    type Output = Result<Vault>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        match shared.result.take() {
+            Some(Ok(result)) => Poll::Ready(result),
+            Some(Err(payload)) => panic::resume_unwind(payload),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Vault {
+    /// Like [`Vault::new`], but loads the vault on a separate thread and returns a future, so the
+    /// file system access doesn't block an async runtime.
+    pub fn new_async(path: impl AsRef<Path>) -> LoadVault {
+        let path = path.as_ref().to_path_buf();
+        LoadVault::spawn(move || Self::new(path))
+    }
+
+    /// Like [`Vault::with_config`], but loads the vault on a separate thread, see
+    /// [`Vault::new_async`].
+    pub fn with_config_async(path: impl AsRef<Path>, config: Config) -> LoadVault {
+        let path = path.as_ref().to_path_buf();
+        LoadVault::spawn(move || Self::with_config(path, config))
+    }
+
+    /// Like [`Vault::open`], but loads the vault on a separate thread, see [`Vault::new_async`].
+    pub fn open_async(path: impl AsRef<Path>) -> LoadVault {
+        let path = path.as_ref().to_path_buf();
+        LoadVault::spawn(move || Self::open(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use std::task::Wake;
+
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// A minimal executor, polling the future on the current thread until it's ready.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_new_async() -> Result<()> {
+        let vault = block_on(Vault::new_async("./tests/vault"))?;
+        assert!(vault.state.len() == 4);
+
+        match block_on(Vault::open_async("./tests/missing")) {
+            Err(Error::InvalidPath(_, _)) => Ok(()),
+            _ => panic!("Expected an invalid path"),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Listing and looking up notes.
+
+use std::cmp::Ordering;
+
+use crate::{Note, State};
+
+/// The property notes are sorted by in [`State::sorted_notes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// The name of the note, compared case-insensitively.
+    Name,
+    /// The creation time of the note, from the `created:` or `date:` field of its metadata block
+    /// if it has one, otherwise of the note file. See [`Note::created_on`].
+    Created,
+    /// The last modification time of the note file.
+    Modified,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// Score how well `query` fuzzily matches `name`, or `None` if the characters of the query don't
+/// all appear in `name` in order. Matching is case-insensitive. Every matched character scores,
+/// with bonuses for runs of consecutive characters and for matches at the start of a word, and a
+/// small penalty for every character skipped in between.
+fn fuzzy_score(query: &str, name: &str) -> Option<i64> {
+    let name = name.chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut last_match: Option<usize> = None;
+    let mut start = 0;
+
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let i = (start..name.len()).find(|&i| name[i].to_lowercase().eq(q.to_lowercase()))?;
+        score += 16;
+        if name[i] == q {
+            score += 1;
+        }
+        let at_word_start = match i.checked_sub(1).map(|prev| name[prev]) {
+            None => true,
+            Some(prev) => {
+                !prev.is_alphanumeric() || (prev.is_lowercase() && name[i].is_uppercase())
+            }
+        };
+        if last_match.is_some() && last_match == i.checked_sub(1) {
+            score += 15;
+        } else if at_word_start {
+            score += 10;
+        }
+        score -= (i - start) as i64;
+        last_match = Some(i);
+        start = i + 1;
+    }
+
+    Some(score)
+}
+
+impl State {
+    /// Returns all notes whose name fuzzily matches `query` together with their score, best match
+    /// first, e.g. for a quick switcher. The characters of the query have to appear in the name in
+    /// the same order, but not necessarily next to each other: `tn2` matches `TestNote02`. Equal
+    /// scores are ordered by the length of the name, then by path.
+    pub fn fuzzy_find(&self, query: &str) -> Vec<(&Note, i64)> {
+        let mut matches = self
+            .notes
+            .iter()
+            .filter_map(|note| Some((note, fuzzy_score(query, &note.name)?)))
+            .collect::<Vec<_>>();
+        matches.sort_by(|(a, a_score), (b, b_score)| {
+            b_score
+                .cmp(a_score)
+                .then_with(|| a.name.chars().count().cmp(&b.name.chars().count()))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        matches
+    }
+
+    /// Returns all notes sorted by `key`. Notes that compare equal, e.g. because a timestamp isn't
+    /// available, are ordered by path so the result is deterministic. Missing timestamps sort before
+    /// any existing one in ascending order.
+    pub fn sorted_notes(&self, key: SortKey, order: SortOrder) -> Vec<&Note> {
+        let mut notes = self.notes.iter().collect::<Vec<_>>();
+        notes.sort_by(|a, b| {
+            let ordering = match key {
+                SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                SortKey::Created => a.created_on.cmp(&b.created_on),
+                SortKey::Modified => a.last_edited.cmp(&b.last_edited),
+            };
+            let ordering = match order {
+                SortOrder::Ascending => ordering,
+                SortOrder::Descending => ordering.reverse(),
+            };
+            match ordering {
+                Ordering::Equal => a.path.cmp(&b.path),
+                ordering => ordering,
+            }
+        });
+        notes
+    }
+
+    /// A single page of [`State::sorted_notes`], skipping the first `offset` notes and returning at
+    /// most `limit` notes.
+    pub fn sorted_notes_page(
+        &self,
+        key: SortKey,
+        order: SortOrder,
+        offset: usize,
+        limit: usize,
+    ) -> Vec<&Note> {
+        self.sorted_notes(key, order)
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Result;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_sorted_notes() -> Result<()> {
+        let mut state = State::new(PathBuf::from("./tests/vault"))?;
+        state.notes.reverse();
+        let names = |notes: Vec<&Note>| notes.iter().map(|n| n.name.clone()).collect::<Vec<_>>();
+
+        let ascending = names(state.sorted_notes(SortKey::Name, SortOrder::Ascending));
+        assert!(ascending == ["TestNote01", "TestNote02", "TestNote03", "TestNote04"]);
+        let page = names(state.sorted_notes_page(SortKey::Name, SortOrder::Descending, 1, 2));
+        assert!(page == ["TestNote03", "TestNote02"]);
+        assert!(state.sorted_notes_page(SortKey::Modified, SortOrder::Ascending, 3, 10).len() == 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fuzzy_find() -> Result<()> {
+        let state = State::from_sources(&[
+            ("Rust Notes", ""),
+            ("rustacean-handbook", ""),
+            ("Trust", ""),
+            ("Python", ""),
+        ]);
+        let names = |query: &str| {
+            state
+                .fuzzy_find(query)
+                .into_iter()
+                .map(|(note, _)| note.name.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(names("rust") == ["rustacean-handbook", "Rust Notes", "Trust"]);
+        assert!(names("Rust")[0] == "Rust Notes");
+        assert!(names("rn")[0] == "Rust Notes" && !names("rn").contains(&"Trust"));
+        assert!(names("rh")[0] == "rustacean-handbook");
+        assert!(names("xyz").is_empty());
+        assert!(names("").len() == 4);
+        assert!(fuzzy_score("tn2", "TestNote02").is_some());
+        assert!(fuzzy_score("abc", "abc") > fuzzy_score("abc", "a_b_c"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+//! Rendering the body of a note.
+
+use pulldown_cmark::{html, CowStr, Event, Parser, Tag, TagEnd, TextMergeStream};
+
+use crate::{Note, Result, State};
+
+/// What happens to `[[wiki links]]` when rendering a note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WikiLinks {
+    /// Keep the links as they are written, brackets included.
+    Preserve,
+    /// Replace each link with its text, the alias in `[[target|alias]]` or the target otherwise.
+    Strip,
+}
+
+/// Replace every `[[wiki link]]` in `text` with the text it displays.
+fn strip_wiki_links(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        let end = match rest[start..].find("]]") {
+            Some(end) => start + end,
+            None => break,
+        };
+        let link = &rest[start + 2..end];
+        let shown = link.split_once('|').map_or(link, |(_, alias)| alias);
+        stripped.push_str(&rest[..start]);
+        stripped.push_str(shown.trim());
+        rest = &rest[end + 2..];
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+/// The markdown events making up the body of a note, without the metadata block.
+pub(crate) fn body_events(contents: &str) -> impl Iterator<Item = Event<'_>> {
+    let mut in_metadata = false;
+    let contents = State::_strip_bom(contents);
+    TextMergeStream::new(Parser::new_ext(contents, State::_parser_options())).filter(move |event| {
+        match event {
+            Event::Start(Tag::MetadataBlock(_)) => in_metadata = true,
+            Event::End(TagEnd::MetadataBlock(_)) => {
+                in_metadata = false;
+                return false;
+            }
+            _ => {}
+        }
+        !in_metadata
+    })
+}
+
+/// The body of a note as plain text, with markdown syntax removed. Blocks are separated by
+/// whitespace, inline formatting like `**bold**` is dropped without splitting the word.
+pub(crate) fn plain_text(contents: &str) -> String {
+    let contents = State::_normalize_newlines(contents);
+    let mut text = String::new();
+    for event in body_events(&contents) {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::End(
+                TagEnd::Emphasis
+                | TagEnd::Strong
+                | TagEnd::Strikethrough
+                | TagEnd::Link
+                | TagEnd::Image,
+            ) => {}
+            Event::End(_) | Event::SoftBreak | Event::HardBreak | Event::Rule => text.push(' '),
+            _ => {}
+        }
+    }
+    text
+}
+
+impl Note {
+    /// Render the body of the note to HTML. The metadata block is never rendered.
+    pub fn to_html(&self, wiki_links: WikiLinks) -> Result<String> {
+        let content = self.content()?;
+        let content = State::_normalize_newlines(&content);
+        let mut in_code = false;
+        let events = body_events(&content).map(|event| match event {
+            Event::Start(Tag::CodeBlock(_)) => {
+                in_code = true;
+                event
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                in_code = false;
+                event
+            }
+            Event::Text(text) if wiki_links == WikiLinks::Strip && !in_code => {
+                Event::Text(CowStr::from(strip_wiki_links(&text)))
+            }
+            event => event,
+        });
+
+        let mut rendered = String::new();
+        html::push_html(&mut rendered, events);
+        Ok(rendered)
+    }
+
+    /// A short plain text preview of the body of the note, e.g. for a list of notes. The metadata
+    /// block and all markdown syntax are left out, `[[wiki links]]` are replaced by their text and
+    /// whitespace is collapsed. If the body is
+    /// longer than `max_chars` characters, it's cut at the last word boundary that fits and `…`
+    /// is appended. A single word longer than `max_chars` is cut in the middle.
+    pub fn preview(&self, max_chars: usize) -> Result<String> {
+        let text = strip_wiki_links(&plain_text(&self.content()?));
+        let words = text.split_whitespace().collect::<Vec<_>>();
+        let full = words.join(" ");
+        if full.chars().count() <= max_chars {
+            return Ok(full);
+        }
+
+        let mut preview = String::new();
+        let mut len = 0;
+        for word in words {
+            let word_len = word.chars().count();
+            let needed = if preview.is_empty() {
+                word_len
+            } else {
+                word_len + 1
+            };
+            if len + needed > max_chars {
+                break;
+            }
+            if !preview.is_empty() {
+                preview.push(' ');
+            }
+            preview.push_str(word);
+            len += needed;
+        }
+        if preview.is_empty() {
+            preview = full.chars().take(max_chars).collect();
+        }
+        preview.push('…');
+        Ok(preview)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_vault;
+    use std::fs;
+
+    #[test]
+    fn test_to_html() -> Result<()> {
+        let path = scratch_vault("html");
+        fs::write(
+            path.join("A.md"),
+            "---\ntags: a\n---\n# Title\n\nSee [[B]] and [[C|this]].\n\n```\n[[D]]\n```\n",
+        )?;
+        let state = State::new(path.clone())?;
+        let note = &state.notes[0];
+
+        let preserved = note.to_html(WikiLinks::Preserve)?;
+        assert!(!preserved.contains("tags"));
+        assert!(preserved.starts_with("<h1>Title</h1>"));
+        assert!(preserved.contains("<p>See [[B]] and [[C|this]].</p>"));
+
+        let stripped = note.to_html(WikiLinks::Strip)?;
+        assert!(stripped.contains("<p>See B and this.</p>"));
+        assert!(stripped.contains("<code>[[D]]\n</code>"));
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_preview() -> Result<()> {
+        let state = State::from_sources(&[
+            (
+                "A",
+                "---\ntags: a\n---\n# Title\n\nSome **bold** and [[Linked]]\ntext.",
+            ),
+            ("B", "Supercalifragilistic"),
+        ]);
+        let note = &state.notes[0];
+
+        assert!(note.preview(100)? == "Title Some bold and Linked text.");
+        assert!(note.preview(20)? == "Title Some bold and…");
+        assert!(note.preview(18)? == "Title Some bold…");
+        assert!(state.notes[1].preview(5)? == "Super…");
+
+        Ok(())
+    }
+}
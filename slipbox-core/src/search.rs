@@ -0,0 +1,100 @@
+//! Full-text search over the bodies of notes, enabled by the `search` feature.
+//!
+//! The words of every note are counted once while the note is loaded, so queries don't have to
+//! read any files.
+
+use std::collections::HashMap;
+
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+use crate::{Note, State};
+
+/// Split text into lowercase words, treating everything that isn't alphanumeric as a separator.
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Count how often each word occurs in the body of a note, ignoring the metadata block.
+pub(crate) fn term_counts(contents: &str) -> HashMap<String, usize> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut in_metadata = false;
+    let contents = State::_normalize_newlines(State::_strip_bom(contents));
+
+    for event in Parser::new_ext(&contents, State::_parser_options()) {
+        match event {
+            Event::Start(Tag::MetadataBlock(_)) => in_metadata = true,
+            Event::End(TagEnd::MetadataBlock(_)) => in_metadata = false,
+            Event::Text(text) | Event::Code(text) if !in_metadata => {
+                for word in words(&text) {
+                    *counts.entry(word).or_default() += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    counts
+}
+
+impl State {
+    /// Search the bodies of all notes for the whitespace separated words of `query`. Words are
+    /// matched case-insensitively against whole words of a note. Every note containing at least one
+    /// of the words is returned, ranked by the total number of hits, best first.
+    pub fn search(&self, query: &str) -> Vec<&Note> {
+        self._search_hits(query)
+            .into_iter()
+            .map(|(note, _)| note)
+            .collect()
+    }
+
+    /// The notes found by [`State::search`] together with their number of hits.
+    pub(crate) fn _search_hits(&self, query: &str) -> Vec<(&Note, usize)> {
+        let query = words(query).collect::<Vec<_>>();
+
+        let mut hits = self
+            .notes
+            .iter()
+            .filter_map(|note| {
+                let terms = self.terms.get(&note.path)?;
+                let count: usize = query.iter().filter_map(|word| terms.get(word)).sum();
+                (count > 0).then_some((note, count))
+            })
+            .collect::<Vec<_>>();
+        // The sort is stable, so notes with equal hits stay in path order.
+        hits.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_vault;
+    use crate::Result;
+    use std::fs;
+
+    #[test]
+    fn test_search() -> Result<()> {
+        let path = scratch_vault("search");
+        fs::write(path.join("A.md"), "---\ntags: rust\n---\nRust is fast.")?;
+        fs::write(path.join("B.md"), "Rust, rust and `rust` again. Also notes.")?;
+        fs::write(path.join("C.md"), "Nothing to see here.")?;
+
+        let state = State::new(path.clone())?;
+        let names = |query| {
+            state
+                .search(query)
+                .iter()
+                .map(|note| note.name.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert!(names("RUST") == ["B", "A"]);
+        assert!(names("fast notes") == ["A", "B"]);
+        assert!(names("tags").is_empty());
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+//! Serializable representations of [`Vault`], [`State`] and [`Note`], enabled by the `serde`
+//! feature. Timestamps are serialized as unix timestamps in seconds and the paths of notes inside a
+//! vault relative to the vault root.
+
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use crate::{Note, State, Vault};
+
+fn unix_timestamp(time: Option<SystemTime>) -> Option<u64> {
+    time.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+fn relative_path(path: &Path, root: Option<&Path>) -> String {
+    root.and_then(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// A note together with the root its paths should be relative to.
+struct NoteRepr<'a> {
+    note: &'a Note,
+    root: Option<&'a Path>,
+}
+
+impl Serialize for NoteRepr<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let note = self.note;
+        let links = note
+            .links
+            .iter()
+            .map(|link| relative_path(link, self.root))
+            .collect::<Vec<_>>();
+
+        let mut s = serializer.serialize_struct("Note", 17)?;
+        s.serialize_field("id", &note.id)?;
+        s.serialize_field("name", &note.name)?;
+        s.serialize_field("path", &relative_path(&note.path, self.root))?;
+        s.serialize_field("tags", &note.tags)?;
+        s.serialize_field("display_tags", &note.display_tags)?;
+        s.serialize_field("frontmatter", &note.frontmatter)?;
+        s.serialize_field("raw_frontmatter", &note.raw_frontmatter)?;
+        s.serialize_field("frontmatter_kind", &note.frontmatter_kind)?;
+        s.serialize_field("aliases", &note.aliases)?;
+        s.serialize_field("draft", &note.draft)?;
+        s.serialize_field("created_on", &unix_timestamp(note.created_on))?;
+        s.serialize_field("last_edited", &unix_timestamp(note.last_edited))?;
+        s.serialize_field("content_hash", &note.content_hash)?;
+        s.serialize_field("links", &links)?;
+        s.serialize_field("raw_links", &note.raw_links)?;
+        s.serialize_field("file_links", &note.file_links)?;
+        s.serialize_field("external_links", &note.external_links)?;
+        s.end()
+    }
+}
+
+impl Serialize for Note {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        NoteRepr {
+            note: self,
+            root: None,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Serialize for State {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let root = Some(self.path.as_path());
+        let notes = self
+            .notes
+            .iter()
+            .map(|note| NoteRepr { note, root })
+            .collect::<Vec<_>>();
+        let errors = self
+            .errors
+            .iter()
+            .map(|(path, e)| (relative_path(path, root), format!("{:?}", e)))
+            .collect::<Vec<_>>();
+
+        let mut s = serializer.serialize_struct("State", 2)?;
+        s.serialize_field("notes", &notes)?;
+        s.serialize_field("errors", &errors)?;
+        s.end()
+    }
+}
+
+impl Serialize for Vault {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Vault", 4)?;
+        s.serialize_field("name", &self.name)?;
+        s.serialize_field("vault_path", &self.vault_path.to_string_lossy())?;
+        s.serialize_field("created_on", &unix_timestamp(self.created_on))?;
+        s.serialize_field("state", &self.state)?;
+        s.end()
+    }
+}
+
+impl Vault {
+    /// Serialize the whole vault to a JSON string.
+    pub fn to_json(&self) -> crate::Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
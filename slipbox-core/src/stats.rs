@@ -0,0 +1,133 @@
+//! Metrics about notes and the vault as a whole.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::render::plain_text;
+use crate::{Note, Result, State};
+
+/// Aggregate metrics of a vault, see [`State::stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VaultStats {
+    /// Number of loaded notes.
+    pub notes: usize,
+    /// Number of distinct tags.
+    pub tags: usize,
+    pub drafts: usize,
+    pub finished: usize,
+    /// Notes without any links from or to other notes, see [`State::orphans`].
+    pub orphans: usize,
+    /// Links that don't resolve to a note, see [`State::broken_links`].
+    pub broken_links: usize,
+    /// Total number of words, see [`State::total_words`]. Only known if the contents of the notes
+    /// are cached through [`crate::Config::cache_content`], so no files have to be read.
+    pub words: Option<usize>,
+}
+
+impl Note {
+    /// Number of words in the body of the note. The metadata block and markdown syntax are not
+    /// counted, so `**bold**` is a single word.
+    pub fn word_count(&self) -> Result<usize> {
+        Ok(plain_text(&self.content()?).split_whitespace().count())
+    }
+
+    /// Estimated time it takes to read the note at `wpm` words per minute. Zero if `wpm` is zero.
+    pub fn reading_time(&self, wpm: usize) -> Result<Duration> {
+        let words = self.word_count()?;
+        if wpm == 0 {
+            return Ok(Duration::ZERO);
+        }
+        Ok(Duration::from_secs_f64(words as f64 * 60.0 / wpm as f64))
+    }
+}
+
+impl State {
+    /// Total number of words over the bodies of all notes, see [`Note::word_count`].
+    pub fn total_words(&self) -> Result<usize> {
+        self.iter().map(Note::word_count).sum()
+    }
+
+    /// Collect the metrics of the vault in a single pass over the notes.
+    pub fn stats(&self) -> VaultStats {
+        let mut tags = HashSet::new();
+        let mut stats = VaultStats {
+            notes: self.len(),
+            broken_links: self.broken_links().len(),
+            words: Some(0),
+            ..VaultStats::default()
+        };
+
+        for note in self.iter() {
+            tags.extend(note.tags.iter());
+            match note.draft {
+                true => stats.drafts += 1,
+                false => stats.finished += 1,
+            }
+            if self._is_orphan(note) {
+                stats.orphans += 1;
+            }
+            let words = note
+                .cached_content
+                .as_deref()
+                .map(|content| plain_text(content).split_whitespace().count());
+            stats.words = stats.words.zip(words).map(|(total, words)| total + words);
+        }
+
+        stats.tags = tags.len();
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_vault;
+    use std::fs;
+
+    #[test]
+    fn test_word_count() -> Result<()> {
+        let path = scratch_vault("words");
+        fs::write(
+            path.join("A.md"),
+            "---\ntags: a b c\n---\n# Some title\n\nOne **bold** and *mixed*words, `code`.\n",
+        )?;
+        fs::write(path.join("B.md"), "- first item\n- second\n")?;
+        let state = State::new(path.clone())?;
+
+        assert!(state.notes[0].word_count()? == 7);
+        assert!(state.notes[1].word_count()? == 3);
+        assert!(state.total_words()? == 10);
+        assert!(state.notes[0].reading_time(210)? == Duration::from_secs(2));
+        assert!(state.notes[0].reading_time(0)? == Duration::ZERO);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats() -> Result<()> {
+        let state = State::from_sources(&[
+            (
+                "A",
+                "---\ntags: rust notes\n---\nSee [[B]] and [[Missing]].",
+            ),
+            ("B", "---\ntags: rust\nstatus: finished\n---\nTwo words"),
+            ("C", "Alone"),
+        ]);
+        let expected = VaultStats {
+            notes: 3,
+            tags: 2,
+            drafts: 2,
+            finished: 1,
+            orphans: 1,
+            broken_links: 1,
+            words: Some(7),
+        };
+        assert!(state.stats() == expected);
+
+        let state = State::new(std::path::PathBuf::from("./tests/vault"))?;
+        assert!(state.stats().notes == 4 && state.stats().words.is_none());
+
+        Ok(())
+    }
+}
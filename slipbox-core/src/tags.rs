@@ -0,0 +1,409 @@
+//! Aggregate queries over the tags of all notes.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{Note, State};
+
+/// The group [`State::group_by_primary_tag`] puts notes without any tags in.
+pub const UNTAGGED: &str = "untagged";
+
+/// The default rule of [`State::invalid_tags`]: a tag has to consist of letters, digits, `_`, `-`
+/// and `/` only, so neither whitespace nor a leading `#` is allowed.
+pub fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty() && tag.chars().all(is_tag_char)
+}
+
+/// Whether `c` may be part of a tag according to [`is_valid_tag`].
+pub(crate) fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '/')
+}
+
+/// How multiple tags are combined when filtering notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchMode {
+    /// Notes must carry every one of the tags.
+    All,
+    /// Notes must carry at least one of the tags.
+    Any,
+}
+
+/// A level of the hierarchy of tags, see [`State::tag_tree`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagNode {
+    /// The last segment of the tag, e.g. `async` for `rust/async`. Empty for the root.
+    pub name: String,
+    /// The full tag, e.g. `rust/async`. Empty for the root.
+    pub tag: String,
+    /// Number of notes tagged with this tag or any tag nested below it, each counted once.
+    pub count: usize,
+    /// Number of notes tagged with exactly this tag. Zero if it's only the parent of other tags,
+    /// e.g. `rust` if notes are only tagged `rust/async`.
+    pub direct: usize,
+    /// The tags nested one level below, sorted by name.
+    pub children: Vec<TagNode>,
+}
+
+impl State {
+    /// Returns all notes matching the given tags according to `mode`. Tags are matched
+    /// case-insensitively. An empty list of tags doesn't filter anything, so all notes are returned
+    /// for both modes.
+    pub fn notes_from_tags(&self, tags: &[String], mode: MatchMode) -> Vec<&Note> {
+        if tags.is_empty() {
+            return self.notes.iter().collect();
+        }
+
+        let tags = tags.iter().map(|tag| tag.to_lowercase()).collect::<Vec<_>>();
+        self.filter(|note| match mode {
+            MatchMode::All => tags.iter().all(|tag| note.has_exact_tag(tag)),
+            MatchMode::Any => tags.iter().any(|tag| note.has_exact_tag(tag)),
+        })
+    }
+
+    /// All tags of the vault in alphabetical order, for display. Every tag is listed once, in the
+    /// spelling of [`State::tag_spellings`], and sorted case-insensitively. Use [`State::tags`] for
+    /// set operations.
+    pub fn sorted_tags(&self) -> Vec<String> {
+        let mut tags = self
+            .tag_spellings()
+            .into_values()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        tags.sort_by_cached_key(|tag| tag.to_lowercase());
+        tags
+    }
+
+    /// The spelling every tag is first written in, from [`Note::display_tags`], by the lowercase
+    /// tag of [`Note::tags`], e.g. to show the keys of [`State::tag_counts`].
+    pub fn tag_spellings(&self) -> HashMap<&str, &str> {
+        let mut spellings = HashMap::new();
+        for note in &self.notes {
+            for (tag, display) in note.tags.iter().zip(&note.display_tags) {
+                spellings.entry(tag.as_str()).or_insert(display.as_str());
+            }
+        }
+        spellings
+    }
+
+    /// Every tag of a note that `is_valid` rejects, together with the note using it, e.g. with
+    /// [`is_valid_tag`] as the rule. The tags are passed to `is_valid` and returned as written in
+    /// the note.
+    pub fn invalid_tags<F>(&self, is_valid: F) -> Vec<(&Note, String)>
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.notes
+            .iter()
+            .flat_map(|note| {
+                note.display_tags
+                    .iter()
+                    .filter(|tag| !is_valid(tag))
+                    .map(move |tag| (note, tag.clone()))
+            })
+            .collect()
+    }
+
+    /// Every tag used in a note that isn't one of the [`crate::Config::allowed_tags`], e.g. a typo,
+    /// together with the note using it. The tags are returned as written in the note. Empty if no
+    /// allowed tags are configured.
+    pub fn unknown_tags(&self) -> Vec<(&Note, String)> {
+        let allowed: HashSet<String> = match &self.config().allowed_tags {
+            Some(allowed) => allowed.iter().map(|tag| tag.to_lowercase()).collect(),
+            None => return vec![],
+        };
+        self.notes
+            .iter()
+            .flat_map(|note| {
+                note.tags
+                    .iter()
+                    .zip(&note.display_tags)
+                    .filter(|(tag, _)| !allowed.contains(*tag))
+                    .map(move |(_, display)| (note, display.clone()))
+            })
+            .collect()
+    }
+
+    /// Count how many notes carry each tag. Tags are already normalized to lowercase, so the counts
+    /// are case-insensitive. A note is only counted once per tag, even if it lists a tag twice.
+    pub fn tag_counts(&self) -> HashMap<String, usize> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for note in &self.notes {
+            for tag in note.tags.iter().collect::<HashSet<_>>() {
+                *counts.entry(tag.clone()).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// All tags starting with `prefix`, e.g. to complete a tag while it's typed. The prefix is
+    /// matched case-insensitively and a leading `#` is ignored, so `#Ru` finds `rust`. The most
+    /// used tags come first, tags used equally often are sorted alphabetically.
+    pub fn tags_with_prefix(&self, prefix: &str) -> Vec<String> {
+        let prefix = prefix.strip_prefix('#').unwrap_or(prefix).to_lowercase();
+        let mut matches = self
+            .tag_counts()
+            .into_iter()
+            .filter(|(tag, _)| tag.starts_with(&prefix))
+            .collect::<Vec<_>>();
+        matches.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        matches.into_iter().map(|(tag, _)| tag).collect()
+    }
+
+    /// The tags of the vault as a tree for hierarchical display, treating `/` as the separator
+    /// of levels. The root stands for the whole vault, its count is the number of notes with any
+    /// tag. A tag like `rust` can both be used itself and have children like `rust/async`.
+    pub fn tag_tree(&self) -> TagNode {
+        // Every level a note is tagged at or below, mapped to the (count, direct) of the level.
+        let mut levels: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        let mut tagged = 0;
+        for note in &self.notes {
+            let mut seen = HashSet::new();
+            for tag in &note.tags {
+                let mut level = tag.as_str();
+                loop {
+                    if seen.insert(level) {
+                        levels.entry(level).or_default().0 += 1;
+                    }
+                    match level.rsplit_once('/') {
+                        Some((parent, _)) => level = parent,
+                        None => break,
+                    }
+                }
+            }
+            for tag in note.tags.iter().collect::<HashSet<_>>() {
+                levels.entry(tag).or_default().1 += 1;
+            }
+            tagged += usize::from(!note.tags.is_empty());
+        }
+
+        let mut root = TagNode {
+            count: tagged,
+            ..TagNode::default()
+        };
+        // Parents sort before their children, so each parent is in the tree by the time its
+        // children are added.
+        for (tag, (count, direct)) in levels {
+            let (parent, name) = tag.rsplit_once('/').unwrap_or(("", tag));
+            let parents = parent.split('/').filter(|_| !parent.is_empty());
+            let mut node = &mut root;
+            for segment in parents {
+                node = node
+                    .children
+                    .iter_mut()
+                    .find(|child| child.name == segment)
+                    .expect("parent tags are added before their children");
+            }
+            node.children.push(TagNode {
+                name: String::from(name),
+                tag: String::from(tag),
+                count,
+                direct,
+                children: vec![],
+            });
+        }
+        root
+    }
+
+    /// Group the notes by their primary tag, the first tag in the order they're declared in the
+    /// metadata block. Notes without tags end up in the [`UNTAGGED`] group. Every note is in
+    /// exactly one group, within a group the notes are in path order.
+    pub fn group_by_primary_tag(&self) -> HashMap<String, Vec<&Note>> {
+        let mut groups: HashMap<String, Vec<&Note>> = HashMap::new();
+        for note in &self.notes {
+            let tag = note.tags.first().map_or(UNTAGGED, String::as_str);
+            groups.entry(String::from(tag)).or_default().push(note);
+        }
+        groups
+    }
+
+    /// Count how many notes carry each pair of tags together. Pairs are unordered and stored with
+    /// the smaller tag first, so `(a, b)` and `(b, a)` are the same key. Like [`State::tag_counts`],
+    /// a note is counted once per pair even if it lists a tag twice.
+    pub fn tag_cooccurrence(&self) -> HashMap<(String, String), usize> {
+        let mut counts: HashMap<(String, String), usize> = HashMap::new();
+        for note in &self.notes {
+            let mut tags = note.tags.iter().collect::<Vec<_>>();
+            tags.sort();
+            tags.dedup();
+            for (i, a) in tags.iter().enumerate() {
+                for b in &tags[i + 1..] {
+                    *counts.entry((a.to_string(), b.to_string())).or_default() += 1;
+                }
+            }
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_vault;
+    use crate::{Result, Vault};
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_notes_from_tags() -> Result<()> {
+        let state = State::new(PathBuf::from("./tests/vault"))?;
+        let names = |tags: &[&str], mode| {
+            let tags = tags.iter().map(|t| String::from(*t)).collect::<Vec<_>>();
+            state
+                .notes_from_tags(&tags, mode)
+                .iter()
+                .map(|note| note.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(names(&["a", "Cool"], MatchMode::All) == ["TestNote03"]);
+        assert!(names(&["a", "rust"], MatchMode::Any) == ["TestNote02", "TestNote03", "TestNote04"]);
+        assert!(names(&["a", "rust"], MatchMode::All).is_empty());
+        assert!(names(&[], MatchMode::Any).len() == 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sorted_tags() -> Result<()> {
+        let state = State::from_sources(&[
+            ("A", "---\ntags: Zettel rust Ünï\n---\n"),
+            ("B", "---\ntags: apple RUST notes ünï\n---\n"),
+        ]);
+        assert!(state.sorted_tags() == ["apple", "notes", "rust", "Zettel", "Ünï"]);
+        assert!(state.tag_spellings()["ünï"] == "Ünï");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_tags() -> Result<()> {
+        let state = State::from_sources(&[
+            ("A", "+++\ntags = [\"rust lang\", \"#todo\", \"area/work\"]\n+++\n"),
+            ("B", "---\ntags: based_on self-hosted ok!\n---\n"),
+        ]);
+        let invalid = |rule: &dyn Fn(&str) -> bool| {
+            state
+                .invalid_tags(rule)
+                .into_iter()
+                .map(|(note, tag)| (note.name.clone(), tag))
+                .collect::<Vec<_>>()
+        };
+
+        let expected = [("A", "rust lang"), ("A", "#todo"), ("B", "ok!")]
+            .map(|(name, tag)| (String::from(name), String::from(tag)));
+        assert!(invalid(&is_valid_tag) == expected);
+        assert!(invalid(&|tag| !tag.contains('/')).len() == 1);
+        assert!(!is_valid_tag(""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_tags() -> Result<()> {
+        let path = scratch_vault("unknown-tags");
+        fs::write(path.join("A.md"), "---\ntags: Rust notes\n---\n")?;
+        fs::write(path.join("B.md"), "---\ntags: rsut\n---\n")?;
+
+        let vault = Vault::open(path.clone())?;
+        assert!(vault.state.unknown_tags().is_empty());
+
+        fs::write(path.join(crate::TAGS_FILE), "# vocabulary\nrust\nNotes\n")?;
+        let vault = Vault::open(path.clone())?;
+        let unknown = vault.state.unknown_tags();
+        assert!(unknown.len() == 1 && unknown[0].0.name == "B" && unknown[0].1 == "rsut");
+
+        fs::write(path.join(crate::CONFIG_FILE), "allowed_tags = [\"rust\", \"rsut\"]\n")?;
+        let vault = Vault::open(path.clone())?;
+        let unknown = vault.state.unknown_tags();
+        assert!(unknown.len() == 1 && unknown[0].1 == "notes");
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_tag_counts() -> Result<()> {
+        let path = scratch_vault("tag-counts");
+        fs::write(path.join("A.md"), "---\ntags: rust notes\n---\n")?;
+        fs::write(path.join("B.md"), "---\ntags: Rust rust\n---\n")?;
+
+        let counts = State::new(path.clone())?.tag_counts();
+        assert!(counts.len() == 2 && counts["rust"] == 2 && counts["notes"] == 1);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_tags_with_prefix() {
+        let state = State::from_sources(&[
+            ("A", "---\ntags: rust ruby notes\n---\n"),
+            ("B", "---\ntags: Ruby run\n---\n"),
+            ("C", "---\ntags: rust\n---\n"),
+        ]);
+        assert!(state.tags_with_prefix("ru") == ["ruby", "rust", "run"]);
+        assert!(state.tags_with_prefix("#RUS") == ["rust"]);
+        assert!(state.tags_with_prefix("").len() == 4);
+        assert!(state.tags_with_prefix("go").is_empty());
+    }
+
+    #[test]
+    fn test_tag_tree() {
+        let state = State::from_sources(&[
+            ("A", "---\ntags: rust rust/async\n---\n"),
+            ("B", "---\ntags: rust/async/tokio rust/cli\n---\n"),
+            ("C", "---\ntags: rust-lang notes\n---\n"),
+            ("D", "No tags."),
+        ]);
+        let tree = state.tag_tree();
+        assert!(tree.count == 3 && tree.name.is_empty());
+        let names = |node: &TagNode| {
+            let names = node.children.iter().map(|c| c.name.clone());
+            names.collect::<Vec<_>>()
+        };
+        assert!(names(&tree) == ["notes", "rust", "rust-lang"]);
+
+        let rust = &tree.children[1];
+        assert!(rust.count == 2 && rust.direct == 1);
+        assert!(names(rust) == ["async", "cli"]);
+        let tokio = &rust.children[0].children[0];
+        assert!(tokio.tag == "rust/async/tokio" && tokio.count == 1 && tokio.direct == 1);
+        assert!(rust.children[0].count == 2 && rust.children[0].direct == 1);
+    }
+
+    #[test]
+    fn test_tag_cooccurrence() -> Result<()> {
+        let path = scratch_vault("cooccurrence");
+        fs::write(path.join("A.md"), "---\ntags: rust notes async\n---\n")?;
+        fs::write(path.join("B.md"), "---\ntags: notes Rust rust\n---\n")?;
+        fs::write(path.join("C.md"), "---\ntags: rust\n---\n")?;
+
+        let pairs = State::new(path.clone())?.tag_cooccurrence();
+        let pair = |a: &str, b: &str| pairs.get(&(String::from(a), String::from(b))).copied();
+        assert!(pairs.len() == 3);
+        assert!(pair("notes", "rust") == Some(2));
+        assert!(pair("async", "notes") == Some(1) && pair("async", "rust") == Some(1));
+        assert!(pair("rust", "notes").is_none());
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_by_primary_tag() -> Result<()> {
+        let state = State::from_sources(&[
+            ("A", "---\ntags: rust notes\n---\n"),
+            ("B", "---\ntags: [Notes, rust]\n---\n"),
+            ("C", "---\ntags:\n  - rust\n---\n"),
+            ("D", "No tags."),
+        ]);
+        let groups = state.group_by_primary_tag();
+        let names = |tag: &str| groups[tag].iter().map(|n| n.name.as_str()).collect::<Vec<_>>();
+
+        assert!(groups.len() == 3);
+        assert!(names("rust") == ["A", "C"]);
+        assert!(names("notes") == ["B"]);
+        assert!(names(UNTAGGED) == ["D"]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+//! Creating notes from templates with `{{placeholders}}`.
+
+use std::fs;
+use std::time::SystemTime;
+
+use tracing::info;
+
+use crate::{date, Error, Note, Result, Vault, TEMPLATES_DIR};
+
+/// Replace every `{{name}}` placeholder in `template` with the value of `name` in `vars`.
+/// Whitespace inside the braces is ignored. Fails on the first placeholder that has no value or
+/// isn't closed.
+fn fill(template: &str, vars: &[(&str, &str)]) -> std::result::Result<String, String> {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| String::from("unclosed placeholder"))?;
+        let name = rest[start + 2..start + end].trim();
+        let value = vars
+            .iter()
+            .rev()
+            .find(|(var, _)| *var == name)
+            .ok_or_else(|| format!("unknown placeholder {{{{{}}}}}", name))?
+            .1;
+        filled.push_str(&rest[..start]);
+        filled.push_str(value);
+        rest = &rest[start + end + 2..];
+    }
+    filled.push_str(rest);
+    Ok(filled)
+}
+
+impl Vault {
+    /// Make a template available to [`Vault::create_from_template`] under `name`, in addition to
+    /// the ones in the templates directory of the vault. Registered templates take precedence.
+    pub fn register_template(&mut self, name: &str, template: &str) {
+        self.templates
+            .insert(String::from(name), String::from(template));
+    }
+
+    /// Create a new note called `name` in the root of the vault from the template `template`,
+    /// either a registered one or `<template>.md` in [`crate::Config::templates_dir`], which
+    /// defaults to [`TEMPLATES_DIR`]. Every `{{placeholder}}` is replaced by its value in `vars`.
+    /// `{{title}}` is the name of the note and `{{date}}` today's date unless `vars` overrides
+    /// them, any other placeholder like `{{tags}}` has to be in `vars`.
+    /// Fails with [`Error::TemplateError`] if there is no such template or a placeholder has no
+    /// value, and like [`Vault::create_note`] otherwise.
+    pub fn create_from_template(
+        &mut self,
+        template: &str,
+        name: &str,
+        vars: &[(&str, &str)],
+    ) -> Result<&Note> {
+        Self::_validate_note_name(name)?;
+        let error = |msg: String| Error::TemplateError(String::from(template), msg);
+
+        let source = match self.templates.get(template) {
+            Some(source) => source.clone(),
+            None => {
+                let templates_dir = self.state.config.templates_dir.as_deref();
+                let path = self
+                    .vault_path
+                    .join(templates_dir.unwrap_or(TEMPLATES_DIR))
+                    .join(format!("{}.md", template));
+                fs::read_to_string(path).map_err(|_| error(String::from("no such template")))?
+            }
+        };
+
+        let today = date::format_date(SystemTime::now());
+        let mut all_vars = vec![("title", name), ("date", today.as_str())];
+        all_vars.extend_from_slice(vars);
+        let contents = fill(&source, &all_vars).map_err(error)?;
+
+        let path = self.vault_path.join(format!("{}.md", name));
+        // Make sure the filled in template is a valid note before anything is written.
+        self.state._parse_note(path.clone(), &contents)?;
+        self._write_new_note(path.clone(), &contents)?;
+        info!("created note {:?} from template {:?}", &path, template);
+        self.state._add_note(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_vault;
+
+    #[test]
+    fn test_fill() {
+        let vars = [("title", "Rust"), ("tags", "[rust]"), ("title", "Override")];
+        assert!(fill("# {{ title }}\n{{tags}}", &vars).as_deref() == Ok("# Override\n[rust]"));
+        assert!(fill("no placeholders", &[]).as_deref() == Ok("no placeholders"));
+        assert!(fill("{{x}}", &vars).unwrap_err() == "unknown placeholder {{x}}");
+        assert!(fill("{{title", &vars).unwrap_err() == "unclosed placeholder");
+    }
+
+    #[test]
+    fn test_create_from_template() -> Result<()> {
+        let path = scratch_vault("template");
+        fs::create_dir_all(path.join(TEMPLATES_DIR))?;
+        fs::write(
+            path.join(TEMPLATES_DIR).join("daily.md"),
+            "---\ntags: {{tags}}\ncreated: {{date}}\n---\n# {{title}}\n",
+        )?;
+        fs::write(
+            path.join(TEMPLATES_DIR).join("Kept.md"),
+            "---\ntags: kept\n---\n",
+        )?;
+
+        // Without a templates directory configured, notes kept in `templates/` still load.
+        let vault = Vault::new(path.clone())?;
+        assert!(vault.state.note_by_name("Kept").is_some());
+
+        let mut vault = Vault::builder(path.clone())
+            .templates_dir(Some(TEMPLATES_DIR))
+            .open()?;
+        assert!(vault.state.is_empty() && vault.state.errors.is_empty());
+
+        let note = vault.create_from_template("daily", "Monday", &[("tags", "[journal]")])?;
+        assert!(note.tags == ["journal"] && note.created_on.is_some());
+        let content = note.content()?;
+        assert!(content.ends_with("---\n# Monday\n"));
+
+        vault.register_template("meeting", "# {{title}} with {{who}}\n");
+        vault.create_from_template("meeting", "Sync", &[("who", "Alex")])?;
+        assert!(fs::read_to_string(path.join("Sync.md"))? == "# Sync with Alex\n");
+
+        let missing = vault.create_from_template("meeting", "Other", &[]);
+        assert!(matches!(missing, Err(Error::TemplateError(name, _)) if name == "meeting"));
+        let unknown = vault.create_from_template("weekly", "Other", &[]);
+        assert!(matches!(unknown, Err(Error::TemplateError(_, _))));
+        assert!(!path.join("Other.md").exists() && vault.state.len() == 2);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+}
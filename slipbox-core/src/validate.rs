@@ -0,0 +1,226 @@
+//! Checking a vault for problems without changing anything, e.g. as a linter in CI.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::render::plain_text;
+use crate::{Config, Error, Vault};
+
+/// The kind of problem a [`ValidationIssue`] reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IssueCategory {
+    /// The vault itself can't be opened, e.g. because the path or the config file is invalid.
+    InvalidVault,
+    /// The note file can't be read.
+    Unreadable,
+    /// The metadata block of the note can't be parsed.
+    MalformedFrontmatter,
+    /// The note doesn't have any tags.
+    MissingTags,
+    /// The note has no content besides its metadata block.
+    EmptyNote,
+    /// The note links to a target that doesn't exist.
+    BrokenLink,
+    /// The note has the same name as another note.
+    DuplicateName,
+    /// The note has the same id as another note.
+    DuplicateId,
+    /// An alias of the note is also used by another note.
+    DuplicateAlias,
+}
+
+/// A single problem found by [`Vault::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValidationIssue {
+    /// The note the problem is about, or the vault for [`IssueCategory::InvalidVault`].
+    pub path: PathBuf,
+    pub category: IssueCategory,
+    /// The line of the note the problem was found at, if it's tied to a single line.
+    pub line: Option<usize>,
+    /// Human readable description of the problem.
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl ValidationIssue {
+    fn new(path: PathBuf, category: IssueCategory, message: String) -> Self {
+        Self {
+            path,
+            category,
+            line: None,
+            message,
+        }
+    }
+}
+
+impl Vault {
+    /// Load the vault at `path` like [`Vault::open`] and report every problem with it: notes that
+    /// can't be read or parsed, notes without tags or content, broken links and duplicate names,
+    /// ids and aliases. Nothing on disk is changed. The issues are sorted by path, an empty list
+    /// means the vault is fine.
+    pub fn validate(path: impl AsRef<Path>) -> Vec<ValidationIssue> {
+        let path = path.as_ref().to_path_buf();
+        // Missing tags and duplicate names are reported as issues instead of failing to load.
+        let config = Self::_load_config(&path).map(|config| Config {
+            require_tags: false,
+            unique_names: false,
+            cache_content: true,
+            ..config
+        });
+        let vault = match config.and_then(|config| Self::with_config(path.clone(), config)) {
+            Ok(vault) => vault,
+            Err(e) => {
+                return vec![ValidationIssue::new(
+                    path,
+                    IssueCategory::InvalidVault,
+                    e.to_string(),
+                )]
+            }
+        };
+
+        let state = &vault.state;
+        let mut issues = vec![];
+
+        for (path, error) in &state.errors {
+            let category = match error {
+                Error::MetaDataError(_, _) | Error::MalformedMetaData(_, _, _) => {
+                    IssueCategory::MalformedFrontmatter
+                }
+                _ => IssueCategory::Unreadable,
+            };
+            issues.push(ValidationIssue {
+                line: error.line(),
+                ..ValidationIssue::new(path.clone(), category, error.to_string())
+            });
+        }
+
+        for note in state.iter() {
+            if note.tags.is_empty() {
+                let message = String::from("note has no tags");
+                issues.push(ValidationIssue::new(
+                    note.path.clone(),
+                    IssueCategory::MissingTags,
+                    message,
+                ));
+            }
+            let content = note.cached_content.as_deref().unwrap_or_default();
+            if plain_text(content).trim().is_empty() {
+                let message = String::from("note is empty");
+                issues.push(ValidationIssue::new(
+                    note.path.clone(),
+                    IssueCategory::EmptyNote,
+                    message,
+                ));
+            }
+        }
+
+        for error in &state.link_errors {
+            // Print the link the way it's written in the note.
+            let (path, message) = match error {
+                Error::BrokenLink(path, target) => (path, format!("broken link [[{}]]", target)),
+                Error::BrokenFileLink(path, target) => (path, format!("broken link ({})", target)),
+                _ => continue,
+            };
+            issues.push(ValidationIssue::new(
+                path.clone(),
+                IssueCategory::BrokenLink,
+                message,
+            ));
+        }
+
+        for conflict in &state.conflicts {
+            let (category, notes) = match conflict {
+                Error::DuplicateNoteName(name) => (
+                    IssueCategory::DuplicateName,
+                    state.iter().filter(|n| n.name == *name).collect(),
+                ),
+                Error::DuplicateNoteId(id) => (
+                    IssueCategory::DuplicateId,
+                    state.iter().filter(|n| n.id == *id).collect(),
+                ),
+                Error::DuplicateAlias(alias) => {
+                    (IssueCategory::DuplicateAlias, state.notes_by_name(alias))
+                }
+                _ => (IssueCategory::InvalidVault, vec![]),
+            };
+            for note in notes {
+                issues.push(ValidationIssue::new(
+                    note.path.clone(),
+                    category,
+                    conflict.to_string(),
+                ));
+            }
+        }
+
+        issues.sort_by(|a, b| (&a.path, a.category).cmp(&(&b.path, b.category)));
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_vault;
+    use crate::Result;
+    use std::fs;
+
+    #[test]
+    fn test_validate() -> Result<()> {
+        let path = scratch_vault("validate");
+        fs::create_dir_all(path.join("sub"))?;
+        fs::write(
+            path.join("A.md"),
+            "---\ntags: a\n---\nSee [[Missing]] and [it](Gone.md).",
+        )?;
+        fs::write(path.join("B.md"), "---\ntags: b\nnot yaml\n---\n")?;
+        fs::write(path.join("C.md"), "---\ntags: c\n---\n")?;
+        fs::write(path.join("Fine.md"), "---\ntags: d\n---\nAll good.")?;
+        fs::write(path.join("sub/A.md"), "No tags here.")?;
+
+        let issues = Vault::validate(path.clone());
+        let malformed = issues.iter().find(|issue| issue.line.is_some()).unwrap();
+        assert!(malformed.path.ends_with("B.md") && malformed.line == Some(3));
+        let prefix = format!("{}:3: ", malformed.path.display());
+        assert!(malformed.to_string().starts_with(&prefix));
+        let broken = issues
+            .iter()
+            .filter(|issue| issue.category == IssueCategory::BrokenLink)
+            .map(|issue| issue.message.as_str())
+            .collect::<Vec<_>>();
+        assert!(broken == ["broken link [[Missing]]", "broken link (Gone.md)"]);
+        let issues = issues
+            .into_iter()
+            .map(|issue| {
+                let name = issue.path.strip_prefix(&path).unwrap().to_path_buf();
+                (name, issue.category)
+            })
+            .collect::<Vec<_>>();
+        let expected = [
+            ("A.md", IssueCategory::BrokenLink),
+            ("A.md", IssueCategory::BrokenLink),
+            ("A.md", IssueCategory::DuplicateName),
+            ("B.md", IssueCategory::MalformedFrontmatter),
+            ("C.md", IssueCategory::EmptyNote),
+            ("sub/A.md", IssueCategory::MissingTags),
+            ("sub/A.md", IssueCategory::DuplicateName),
+        ]
+        .map(|(name, category)| (PathBuf::from(name), category));
+        assert!(issues == expected);
+
+        let issues = Vault::validate(path.join("missing"));
+        assert!(issues.len() == 1 && issues[0].category == IssueCategory::InvalidVault);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+}
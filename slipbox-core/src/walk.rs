@@ -0,0 +1,148 @@
+//! Walking the directories of a vault one entry at a time.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use tracing::debug;
+
+use crate::{Error, Note, Result, State, Vault};
+
+/// Walks a vault depth first and yields the paths of its note files, directory entries in sorted
+/// order. Symlinks are followed. Directories are tracked by their canonical path so that symlinked
+/// directories can't cause infinite loops, a directory reachable through several paths is only
+/// walked the first time. Directories deeper than [`crate::Config::max_depth`] are left out. A
+/// directory that can't be read yields its path and error and is skipped.
+pub(crate) struct NotePaths<'a> {
+    state: &'a State,
+    /// The root of the vault until the walk starts.
+    root: Option<PathBuf>,
+    /// The entries left to visit of every directory on the way down, in reverse order.
+    stack: Vec<Vec<PathBuf>>,
+    visited: HashSet<PathBuf>,
+}
+
+impl<'a> NotePaths<'a> {
+    pub(crate) fn new(state: &'a State) -> Self {
+        Self {
+            state,
+            root: Some(state.path.clone()),
+            stack: vec![],
+            visited: HashSet::new(),
+        }
+    }
+
+    fn _enter(&mut self, path: PathBuf) -> std::result::Result<(), (PathBuf, Error)> {
+        if !self
+            .visited
+            .insert(fs::canonicalize(&path).map_err(|e| (path.clone(), Error::io(&path, e)))?)
+        {
+            debug!("skipping already visited directory: {:?}", &path);
+            return Ok(());
+        }
+
+        // Sort entries so that the order of notes doesn't depend on the platform.
+        let mut entries = fs::read_dir(&path)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|e| e.path()))
+                    .collect::<std::result::Result<Vec<_>, _>>()
+            })
+            .map_err(|e| (path.clone(), Error::io(&path, e)))?;
+        entries.sort_by(|a, b| b.cmp(a));
+        self.stack.push(entries);
+        Ok(())
+    }
+}
+
+impl Iterator for NotePaths<'_> {
+    type Item = std::result::Result<PathBuf, (PathBuf, Error)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            if let Err(e) = self._enter(root) {
+                return Some(Err(e));
+            }
+        }
+
+        loop {
+            let path = match self.stack.last_mut()?.pop() {
+                Some(path) => path,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+
+            if self.state._is_skipped(&path, path.is_dir()) {
+                debug!("skipping ignored path: {:?}", &path);
+            } else if path.is_dir() {
+                // A directory among the entries on top of the stack is as many levels below the
+                // root as there are directories on the stack.
+                let depth = self.stack.len();
+                if self.state.config.max_depth.is_some_and(|max| depth > max) {
+                    debug!("skipping directory below the maximum depth: {:?}", &path);
+                } else if let Err(e) = self._enter(path) {
+                    return Some(Err(e));
+                }
+            } else if path.is_file() && self.state.config.is_note(&path) {
+                return Some(Ok(path));
+            }
+        }
+    }
+}
+
+impl Vault {
+    /// Walk the vault again and parse its notes one at a time, only as far as the iterator is
+    /// advanced, e.g. when only the first few notes are needed. The notes come in the same order
+    /// [`State::new`] loads them, but their links aren't resolved since that needs all of them,
+    /// only [`Note::raw_links`] is set. A note file reachable through several symlinks is only
+    /// parsed once, under the first path it's found at. Notes and directories that can't be read
+    /// yield their error and the walk goes on.
+    pub fn notes_lazy(&self) -> impl Iterator<Item = Result<Note>> + '_ {
+        let mut targets = HashSet::new();
+        NotePaths::new(&self.state)
+            .filter(move |path| match path {
+                Ok(path) => fs::canonicalize(path).map_or(true, |target| targets.insert(target)),
+                Err(_) => true,
+            })
+            .map(|path| Ok(self.state._read_note(&path.map_err(|(_, e)| e)?)?.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_vault;
+
+    #[test]
+    fn test_notes_lazy() -> Result<()> {
+        let path = scratch_vault("lazy");
+        fs::create_dir_all(path.join("sub"))?;
+        fs::write(path.join("A.md"), "---\ntags: a\n---\n[[B]]")?;
+        fs::write(path.join("sub/B.md"), "---\ntags: b\n---\n")?;
+        fs::write(path.join("C.md"), "---\ndraft: maybe\n---\n")?;
+        fs::write(path.join("D.md"), "---\ntags: d\n---\n")?;
+
+        let vault = Vault::new(path.clone())?;
+        let first = vault.notes_lazy().next().unwrap()?;
+        assert!(first.name == "A" && first.raw_links == ["B"] && first.links.is_empty());
+
+        let results = vault.notes_lazy().collect::<Vec<_>>();
+        assert!(results.len() == 4 && results[1].is_err());
+        let names = results
+            .iter()
+            .filter_map(|note| Some(note.as_ref().ok()?.name.as_str()))
+            .collect::<Vec<_>>();
+        assert!(names == ["A", "D", "B"]);
+        let loaded = vault
+            .state
+            .iter()
+            .map(|n| n.name.as_str())
+            .collect::<Vec<_>>();
+        assert!(names == loaded);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+}
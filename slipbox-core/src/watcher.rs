@@ -0,0 +1,98 @@
+//! Keeping a [`Vault`] in sync with the files on disk, enabled by the `watcher` feature.
+//!
+//! The watcher polls the vault through [`State::refresh`], which only re-parses notes that changed,
+//! so no platform specific file system notifications are needed. Only note files are looked at,
+//! writes to attachments or to paths the vault ignores never trigger the callback. Consumers
+//! that receive raw file system events from elsewhere can apply the same filtering through
+//! [`State::is_tracked`].
+
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use tracing::debug;
+
+use crate::{Result, State, Vault};
+
+impl Vault {
+    /// Watch the vault directory and keep the state up to date until `callback` breaks. The vault
+    /// is checked for changes every `poll_interval`. Once a change is found, the watcher keeps
+    /// collecting changes until none arrive for `debounce`, so a single editor save that touches a
+    /// file several times only invokes `callback` once. The callback receives the refreshed state
+    /// and the paths of all notes that were added, modified or removed. Files that aren't notes,
+    /// see [`State::is_tracked`], are never reported.
+    pub fn watch<F>(
+        &mut self,
+        poll_interval: Duration,
+        debounce: Duration,
+        mut callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&State, &[PathBuf]) -> ControlFlow<()>,
+    {
+        loop {
+            let mut changed = self.state.refresh()?;
+            if changed.is_empty() {
+                thread::sleep(poll_interval);
+                continue;
+            }
+
+            loop {
+                thread::sleep(debounce);
+                let more = self.state.refresh()?;
+                if more.is_empty() {
+                    break;
+                }
+                changed.extend(more);
+            }
+            changed.sort();
+            changed.dedup();
+
+            debug!("vault changed: {:?}", &changed);
+            if callback(&self.state, &changed).is_break() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_vault;
+    use std::fs;
+
+    #[test]
+    fn test_watch() -> Result<()> {
+        let path = scratch_vault("watch");
+        fs::write(path.join("A.md"), "---\ntags: a\n---\n")?;
+        let mut vault = Vault::new(path.clone())?;
+
+        let writer = {
+            let path = path.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                fs::write(path.join("image.png"), "not a note").unwrap();
+                fs::write(path.join("B.md"), "---\ntags: b\n---\n").unwrap();
+            })
+        };
+
+        let mut calls = 0;
+        vault.watch(
+            Duration::from_millis(10),
+            Duration::from_millis(30),
+            |state, changed| {
+                calls += 1;
+                assert!(changed == [path.join("B.md")]);
+                assert!(state.notes.len() == 2);
+                ControlFlow::Break(())
+            },
+        )?;
+        writer.join().unwrap();
+        assert!(calls == 1);
+
+        fs::remove_dir_all(path)?;
+        Ok(())
+    }
+}
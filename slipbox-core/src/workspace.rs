@@ -0,0 +1,193 @@
+//! Querying several vaults at once.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::{Note, Result, State, Vault};
+
+/// A set of vaults that are queried together, e.g. separate vaults for work and private notes.
+/// Every note found through the workspace comes with the vault it belongs to. Links only resolve
+/// within their own vault, links to a note of another vault are reported by
+/// [`Workspace::cross_vault_links`].
+#[derive(Debug, Default)]
+pub struct Workspace {
+    pub vaults: Vec<Vault>,
+}
+
+impl Workspace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open all vaults at `paths` with [`Vault::open`], failing if any of them can't be opened.
+    pub fn open<I, P>(paths: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let vaults = paths
+            .into_iter()
+            .map(Vault::open)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { vaults })
+    }
+
+    pub fn add(&mut self, vault: Vault) {
+        self.vaults.push(vault);
+    }
+
+    /// Look up a vault by its name.
+    pub fn vault(&self, name: &str) -> Option<&Vault> {
+        self.vaults.iter().find(|vault| vault.name == name)
+    }
+
+    /// The vault `note` belongs to.
+    pub fn vault_of(&self, note: &Note) -> Option<&Vault> {
+        self.vaults
+            .iter()
+            .find(|vault| vault.state.iter().any(|other| std::ptr::eq(other, note)))
+    }
+
+    /// Iterate over the notes of all vaults, in the order the vaults were added.
+    pub fn iter(&self) -> impl Iterator<Item = (&Vault, &Note)> {
+        self.vaults
+            .iter()
+            .flat_map(|vault| vault.state.iter().map(move |note| (vault, note)))
+    }
+
+    /// All tags used in any of the vaults.
+    pub fn tags(&self) -> HashSet<&str> {
+        self.vaults
+            .iter()
+            .flat_map(|vault| vault.state.tags())
+            .collect()
+    }
+
+    /// All notes carrying `tag` in any of the vaults.
+    pub fn notes_from_tag(&self, tag: &str) -> Vec<(&Vault, &Note)> {
+        self.vaults
+            .iter()
+            .flat_map(|vault| {
+                vault
+                    .state
+                    .notes_from_tag(tag)
+                    .into_iter()
+                    .map(move |note| (vault, note))
+            })
+            .collect()
+    }
+
+    /// Look up a note by name like [`State::note_by_name`], in the vaults in the order they were
+    /// added. The name can be qualified with the name of a vault, like `work/Meetings`, to only
+    /// look in that vault.
+    pub fn note_by_name(&self, name: &str) -> Option<(&Vault, &Note)> {
+        if let Some((vault, name)) = name.split_once('/') {
+            if let Some(vault) = self.vault(vault) {
+                return vault.state.note_by_name(name).map(|note| (vault, note));
+            }
+        }
+        self.vaults
+            .iter()
+            .find_map(|vault| vault.state.note_by_name(name).map(|note| (vault, note)))
+    }
+
+    /// Links that don't resolve within their own vault but match a note of another vault, as the
+    /// linking note, the target as it's written and the vault with the matching note.
+    pub fn cross_vault_links(&self) -> Vec<(&Note, String, &Vault)> {
+        self.vaults
+            .iter()
+            .enumerate()
+            .flat_map(|(i, vault)| {
+                vault
+                    .state
+                    .broken_links()
+                    .into_iter()
+                    .filter_map(move |(note, target)| {
+                        let name = State::_link_name(&target, &vault.state.config);
+                        let other = self
+                            .vaults
+                            .iter()
+                            .enumerate()
+                            .find(|&(j, other)| j != i && other.state.note_by_name(name).is_some())?
+                            .1;
+                        Some((note, target, other))
+                    })
+            })
+            .collect()
+    }
+
+    /// Search all vaults like [`State::search`]. Notes of all vaults are ranked together by their
+    /// number of hits.
+    #[cfg(feature = "search")]
+    pub fn search(&self, query: &str) -> Vec<(&Vault, &Note)> {
+        let mut hits = self
+            .vaults
+            .iter()
+            .flat_map(|vault| {
+                vault
+                    .state
+                    ._search_hits(query)
+                    .into_iter()
+                    .map(move |(note, count)| (vault, note, count))
+            })
+            .collect::<Vec<_>>();
+        // The sort is stable, so notes with equal hits stay in vault and path order.
+        hits.sort_by_key(|&(_, _, count)| std::cmp::Reverse(count));
+        hits.into_iter()
+            .map(|(vault, note, _)| (vault, note))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_vault;
+    use std::fs;
+
+    #[test]
+    fn test_workspace() -> Result<()> {
+        let work = scratch_vault("workspace-work");
+        let private = scratch_vault("workspace-private");
+        fs::write(
+            work.join("Meetings.md"),
+            "---\ntags: work\n---\nSee [[Recipes]] and [[Gone]].",
+        )?;
+        fs::write(work.join("Shared.md"), "---\ntags: rust\n---\n")?;
+        fs::write(
+            private.join("Recipes.md"),
+            "---\ntags: food\n---\nPasta and pasta.",
+        )?;
+        fs::write(private.join("Shared.md"), "---\ntags: rust\n---\n")?;
+
+        let workspace = Workspace::open([&work, &private])?;
+        assert!(workspace.iter().count() == 4);
+        assert!(workspace.tags() == HashSet::from(["work", "rust", "food"]));
+        assert!(workspace.notes_from_tag("rust").len() == 2);
+
+        let (vault, note) = workspace.note_by_name("Recipes").unwrap();
+        assert!(vault.vault_path == private && note.name == "Recipes");
+        assert!(workspace.vault_of(note).unwrap().vault_path == private);
+        let (vault, _) = workspace.note_by_name("Shared").unwrap();
+        assert!(vault.vault_path == work);
+        let qualified = format!("{}/Shared", workspace.vaults[1].name);
+        let (vault, _) = workspace.note_by_name(&qualified).unwrap();
+        assert!(vault.vault_path == private);
+        assert!(workspace.note_by_name("Missing").is_none());
+
+        let cross = workspace.cross_vault_links();
+        assert!(cross.len() == 1);
+        assert!(cross[0].0.name == "Meetings" && cross[0].1 == "Recipes");
+        assert!(cross[0].2.vault_path == private);
+
+        #[cfg(feature = "search")]
+        {
+            let found = workspace.search("pasta");
+            assert!(found.len() == 1 && found[0].1.name == "Recipes");
+        }
+
+        fs::remove_dir_all(work)?;
+        fs::remove_dir_all(private)?;
+        Ok(())
+    }
+}